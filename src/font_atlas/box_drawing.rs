@@ -0,0 +1,349 @@
+/*
+ *  box drawing, block elements and powerline separators drawn to fit
+ *  the cell exactly
+ *
+ *  fonts draw these at their own size, so at most sizes the lines of
+ *  tmux borders and htop bars don't meet from one cell to the next.
+ *  drawn here they are exactly a cell big, one coverage byte a pixel,
+ *  with every line in the same place in every cell so neighbours join
+ *
+ *      U+2500 - U+257F     box drawing
+ *      U+2580 - U+259F     block elements
+ *      U+E0B0 - U+E0B3     powerline triangles and chevrons
+ */
+
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const DOUBLE: u8 = 3;
+
+// weight of the line from the centre towards up, right, down and left
+// for each box drawing char, dashes, arcs and diagonals are drawn
+// from these and changed after
+const LINES: [[u8; 4]; 128] = [
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // 2500
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // 2504
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // 2508
+    [0, 1, 1, 0], [0, 2, 1, 0], [0, 1, 2, 0], [0, 2, 2, 0], // 250C
+    [0, 0, 1, 1], [0, 0, 1, 2], [0, 0, 2, 1], [0, 0, 2, 2], // 2510
+    [1, 1, 0, 0], [1, 2, 0, 0], [2, 1, 0, 0], [2, 2, 0, 0], // 2514
+    [1, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 1], [2, 0, 0, 2], // 2518
+    [1, 1, 1, 0], [1, 2, 1, 0], [2, 1, 1, 0], [1, 1, 2, 0], // 251C
+    [2, 1, 2, 0], [2, 2, 1, 0], [1, 2, 2, 0], [2, 2, 2, 0], // 2520
+    [1, 0, 1, 1], [1, 0, 1, 2], [2, 0, 1, 1], [1, 0, 2, 1], // 2524
+    [2, 0, 2, 1], [2, 0, 1, 2], [1, 0, 2, 2], [2, 0, 2, 2], // 2528
+    [0, 1, 1, 1], [0, 1, 1, 2], [0, 2, 1, 1], [0, 2, 1, 2], // 252C
+    [0, 1, 2, 1], [0, 1, 2, 2], [0, 2, 2, 1], [0, 2, 2, 2], // 2530
+    [1, 1, 0, 1], [1, 1, 0, 2], [1, 2, 0, 1], [1, 2, 0, 2], // 2534
+    [2, 1, 0, 1], [2, 1, 0, 2], [2, 2, 0, 1], [2, 2, 0, 2], // 2538
+    [1, 1, 1, 1], [1, 1, 1, 2], [1, 2, 1, 1], [1, 2, 1, 2], // 253C
+    [2, 1, 1, 1], [1, 1, 2, 1], [2, 1, 2, 1], [2, 1, 1, 2], // 2540
+    [2, 2, 1, 1], [1, 1, 2, 2], [1, 2, 2, 1], [2, 2, 1, 2], // 2544
+    [1, 2, 2, 2], [2, 1, 2, 2], [2, 2, 2, 1], [2, 2, 2, 2], // 2548
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // 254C
+    [0, 3, 0, 3], [3, 0, 3, 0], [0, 3, 1, 0], [0, 1, 3, 0], // 2550
+    [0, 3, 3, 0], [0, 0, 1, 3], [0, 0, 3, 1], [0, 0, 3, 3], // 2554
+    [1, 3, 0, 0], [3, 1, 0, 0], [3, 3, 0, 0], [1, 0, 0, 3], // 2558
+    [3, 0, 0, 1], [3, 0, 0, 3], [1, 3, 1, 0], [3, 1, 3, 0], // 255C
+    [3, 3, 3, 0], [1, 0, 1, 3], [3, 0, 3, 1], [3, 0, 3, 3], // 2560
+    [0, 3, 1, 3], [0, 1, 3, 1], [0, 3, 3, 3], [1, 3, 0, 3], // 2564
+    [3, 1, 0, 1], [3, 3, 0, 3], [1, 3, 1, 3], [3, 1, 3, 1], // 2568
+    [3, 3, 3, 3], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], // 256C
+    [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], // 2570
+    [0, 0, 0, 1], [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], // 2574
+    [0, 0, 0, 2], [2, 0, 0, 0], [0, 2, 0, 0], [0, 0, 2, 0], // 2578
+    [0, 2, 0, 1], [1, 0, 2, 0], [0, 1, 0, 2], [2, 0, 1, 0], // 257C
+];
+
+// chars drawn here rather than by a font
+pub fn is_procedural(c: char) -> bool {
+    return matches!(c, '\u{2500}'..='\u{259f}' | '\u{e0b0}'..='\u{e0b3}');
+}
+
+// coverage of a char filling a cell, None if it isn't drawn here
+pub fn rasterize(c: char, width: usize, height: usize) -> Option<Vec<u8>> {
+    if !is_procedural(c) || width == 0 || height == 0 {
+        return None;
+    }
+    let mut canvas = Canvas { width, height, pixels: vec![0; width * height],
+        light: ((width as f32 / 8.0).round() as usize).max(1) };
+    match c {
+        '\u{2500}'..='\u{257f}' => canvas.box_drawing(c),
+        '\u{2580}'..='\u{259f}' => canvas.block(c),
+        _ => canvas.powerline(c),
+    }
+    return Some(canvas.pixels);
+}
+
+// start and end of a line of a weight across size pixels, centred
+// the same way in every cell
+fn span(size: usize, weight: u8, light: usize) -> (usize, usize) {
+    let thickness = match weight {
+        HEAVY => 2 * light,
+        // two light lines a light line apart
+        DOUBLE => 3 * light,
+        _ => light,
+    }.min(size);
+    let start = (size - thickness) / 2;
+    return (start, start + thickness);
+}
+
+fn segment_distance((x, y): (f32, f32), (x0, y0): (f32, f32), (x1, y1): (f32, f32))
+    -> f32 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let along = (((x - x0) * dx + (y - y0) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+    return (x - x0 - along * dx).hypot(y - y0 - along * dy);
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    // thickness of a light line
+    light: usize,
+}
+
+impl Canvas {
+    fn fill(&mut self, (x0, y0): (usize, usize), (x1, y1): (usize, usize), value: u8) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                self.pixels[y * self.width + x] = value;
+            }
+        }
+    }
+
+    // fills what inside takes in from 4x4 samples a pixel, keeping
+    // whatever is already brighter
+    fn shape(&mut self, inside: impl Fn(f32, f32) -> bool) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let hits = (0..16).filter(|sample| {
+                    return inside(x as f32 + (sample % 4) as f32 * 0.25 + 0.125,
+                                  y as f32 + (sample / 4) as f32 * 0.25 + 0.125);
+                }).count();
+                let pixel = &mut self.pixels[y * self.width + x];
+                *pixel = (*pixel).max((hits * 255 / 16) as u8);
+            }
+        }
+    }
+
+    fn box_drawing(&mut self, c: char) {
+        let idx = c as usize - 0x2500;
+        for arm in 0..4 {
+            self.arm(LINES[idx], arm);
+        }
+
+        let (width, height) = (self.width as f32, self.height as f32);
+        let light = self.light as f32;
+        match c {
+            '\u{2504}' | '\u{2505}' => self.dashes(3, false),
+            '\u{2506}' | '\u{2507}' => self.dashes(3, true),
+            '\u{2508}' | '\u{2509}' => self.dashes(4, false),
+            '\u{250a}' | '\u{250b}' => self.dashes(4, true),
+            '\u{254c}' | '\u{254d}' => self.dashes(2, false),
+            '\u{254e}' | '\u{254f}' => self.dashes(2, true),
+            // which way the arc leaves the centre, (right, down)
+            '\u{256d}' => self.arc(1.0, 1.0),
+            '\u{256e}' => self.arc(-1.0, 1.0),
+            '\u{256f}' => self.arc(-1.0, -1.0),
+            '\u{2570}' => self.arc(1.0, -1.0),
+            '\u{2571}' | '\u{2572}' | '\u{2573}' => self.shape(|x, y| {
+                let rising = segment_distance((x, y), (width, 0.0), (0.0, height));
+                let falling = segment_distance((x, y), (0.0, 0.0), (width, height));
+                return match c {
+                    '\u{2571}' => rising,
+                    '\u{2572}' => falling,
+                    _ => rising.min(falling),
+                } <= light / 2.0;
+            }),
+            _ => {}
+        }
+    }
+
+    // line from the centre to the edge in one direction, arm is an index
+    // into a LINES entry. lines run far enough into the centre to meet
+    // the lines across them. where a line ends at a double line, or each
+    // half of a double line meets a line on its side, it stops at the
+    // nearest half so corners and tees keep the gap
+    fn arm(&mut self, arms: [u8; 4], arm: usize) {
+        let weight = arms[arm];
+        if weight == 0 {
+            return;
+        }
+        let vertical = arm.is_multiple_of(2);
+        let (along, across) = if vertical {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        // up and left run from 0 to the centre, right and down from the
+        // centre to the edge
+        let towards_zero = arm == 0 || arm == 3;
+        // lines across this one, left and right of up and down,
+        // above and below left and right
+        let (before, after) = if vertical { (arms[3], arms[1]) } else { (arms[0], arms[2]) };
+        let light = self.light;
+        // how far along a line across has to go to reach its near or far edge
+        let near = |weight: u8| {
+            let (start, end) = span(along, weight, light);
+            match (weight == DOUBLE, towards_zero) {
+                (true, true) => start + light,
+                (true, false) => end - light,
+                (false, true) => end,
+                (false, false) => start,
+            }
+        };
+        let far = |weight: u8| {
+            let (start, end) = span(along, weight, light);
+            if towards_zero { end } else { start }
+        };
+        let furthest = |a: usize, b: usize| if towards_zero { a.max(b) } else { a.min(b) };
+
+        // (start, end) across of each part and how far along it goes
+        let mut parts = Vec::new();
+        let (start, end) = span(across, weight, light);
+        if weight == DOUBLE {
+            for (side, (near_side, far_side)) in [((start, start + light), (before, after)),
+                                                  ((end - light, end), (after, before))] {
+                let reach = if near_side != 0 {
+                    near(near_side)
+                } else if far_side != 0 {
+                    far(far_side)
+                } else {
+                    along / 2
+                };
+                parts.push((side, reach));
+            }
+        } else {
+            let reach = match (before, after) {
+                (0, 0) => far(weight),
+                (0, other) | (other, 0) => far(other),
+                // a tee
+                (before, after) if arms[(arm + 2) % 4] == 0 =>
+                    furthest(near(before), near(after)),
+                (before, after) => furthest(far(before), far(after)),
+            };
+            parts.push(((start, end), reach));
+        }
+
+        for ((start, end), reach) in parts {
+            let (from, to) = if towards_zero { (0, reach) } else { (reach, along) };
+            if vertical {
+                self.fill((start, from), (end, to), 255);
+            } else {
+                self.fill((from, start), (to, end), 255);
+            }
+        }
+    }
+
+    // cuts gaps in a straight line so it is count dashes a cell, the gaps
+    // straddle the cell edges so dashes are evenly spaced across cells
+    fn dashes(&mut self, count: usize, vertical: bool) {
+        let along = if vertical { self.height } else { self.width };
+        let gap = (along / count / 2).max(1);
+        for dash in 0..=count {
+            let centre = dash * along / count;
+            let (from, to) = (centre.saturating_sub(gap / 2), centre + gap - gap / 2);
+            if vertical {
+                self.fill((0, from), (self.width, to), 0);
+            } else {
+                self.fill((from, 0), (to, self.height), 0);
+            }
+        }
+    }
+
+    // rounded corner joining light lines leaving the centre towards
+    // (right, down), each -1.0 or 1.0
+    fn arc(&mut self, right: f32, down: f32) {
+        let (x_start, x_end) = span(self.width, LIGHT, self.light);
+        let (y_start, y_end) = span(self.height, LIGHT, self.light);
+        // middle of each line, and the room to the edges it heads for
+        let (middle_x, middle_y) = ((x_start + x_end) as f32 / 2.0,
+                                    (y_start + y_end) as f32 / 2.0);
+        let room_x = if right > 0.0 { self.width as f32 - middle_x } else { middle_x };
+        let room_y = if down > 0.0 { self.height as f32 - middle_y } else { middle_y };
+        let radius = room_x.min(room_y);
+        let (centre_x, centre_y) = (middle_x + right * radius, middle_y + down * radius);
+        let half = self.light as f32 / 2.0;
+
+        self.shape(|x, y| {
+            let (dx, dy) = ((x - centre_x) * right, (y - centre_y) * down);
+            return match (dx <= 0.0, dy <= 0.0) {
+                // the quarter circle
+                (true, true) => ((x - centre_x).hypot(y - centre_y) - radius).abs() <= half,
+                // straight on to the edge when the cell isn't square
+                (true, false) => (x - middle_x).abs() <= half,
+                (false, true) => (y - middle_y).abs() <= half,
+                (false, false) => false,
+            };
+        });
+    }
+
+    fn block(&mut self, c: char) {
+        let (width, height) = (self.width, self.height);
+        let (half_x, half_y) = (width / 2, height / 2);
+        // rounded down like the halves so the two sides of a split meet
+        let eighth_x = |n: usize| (width * n / 8).max(1);
+        let eighth_y = |n: usize| (height * n / 8).max(1);
+        match c {
+            '\u{2580}' => self.fill((0, 0), (width, half_y), 255),
+            // lower eighths up to the full block
+            '\u{2581}'..='\u{2588}' => {
+                let n = c as usize - 0x2580;
+                let top = (height * (8 - n) / 8).min(height - 1);
+                self.fill((0, top), (width, height), 255);
+            }
+            // left eighths down from seven
+            '\u{2589}'..='\u{258f}' => {
+                let n = 0x2590 - c as usize;
+                self.fill((0, 0), (eighth_x(n), height), 255);
+            }
+            '\u{2590}' => self.fill((half_x, 0), (width, height), 255),
+            // shades are flat so they tile without a pattern
+            '\u{2591}' => self.fill((0, 0), (width, height), 64),
+            '\u{2592}' => self.fill((0, 0), (width, height), 128),
+            '\u{2593}' => self.fill((0, 0), (width, height), 191),
+            '\u{2594}' => self.fill((0, 0), (width, eighth_y(1)), 255),
+            '\u{2595}' => self.fill((width - eighth_x(1), 0), (width, height), 255),
+            _ => {
+                // quadrants, upper left, upper right, lower left, lower right
+                let quadrants = match c {
+                    '\u{2596}' => [false, false, true, false],
+                    '\u{2597}' => [false, false, false, true],
+                    '\u{2598}' => [true, false, false, false],
+                    '\u{2599}' => [true, false, true, true],
+                    '\u{259a}' => [true, false, false, true],
+                    '\u{259b}' => [true, true, true, false],
+                    '\u{259c}' => [true, true, false, true],
+                    '\u{259d}' => [false, true, false, false],
+                    '\u{259e}' => [false, true, true, false],
+                    _ => [false, true, true, true],
+                };
+                let corners = [((0, 0), (half_x, half_y)), ((half_x, 0), (width, half_y)),
+                               ((0, half_y), (half_x, height)),
+                               ((half_x, half_y), (width, height))];
+                for (on, (from, to)) in quadrants.iter().zip(corners) {
+                    if *on {
+                        self.fill(from, to, 255);
+                    }
+                }
+            }
+        }
+    }
+
+    // triangles and chevrons pointing right then left, tip at the
+    // middle of the far edge
+    fn powerline(&mut self, c: char) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let half = self.light as f32 / 2.0;
+        self.shape(|x, y| {
+            let x = if matches!(c, '\u{e0b2}' | '\u{e0b3}') { width - x } else { x };
+            let tip = (width, height / 2.0);
+            return match c {
+                '\u{e0b0}' | '\u{e0b2}' =>
+                    x / width + (2.0 * y / height - 1.0).abs() <= 1.0,
+                _ => segment_distance((x, y), (0.0, 0.0), tip)
+                    .min(segment_distance((x, y), (0.0, height), tip)) <= half,
+            };
+        });
+    }
+}
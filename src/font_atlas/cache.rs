@@ -0,0 +1,173 @@
+/*
+ *  on disk cache of the glpyh atlas
+ *
+ *  every font, size and raster setup gets a directory named after a hash
+ *  of them, holding a png for each page and a lookup file. pages are
+ *  greyscale or rgba pngs of the coverage depending on the antialiasing:
+ *
+ *      hermitshell atlas 5
+ *      page <x> <y> <width> ...                 skyline of each page
+ *      glpyh <face> <codepoint> <w> <h> <x> <y> <page> <xmin> <ymin>
+ *      id <face> <glpyh id> <w> <h> <x> <y> <page> <xmin> <ymin>
+ *
+ *  face is the index in Face::ALL, id lines are shaped glpyhs. clusters
+ *  of marks are numbered per session so they aren't saved, nor is the
+ *  colour page
+ *
+ *  anything that doesn't look like that is ignored and the atlas
+ *  starts out empty
+ */
+use crate::font_atlas::font_atlas::{Antialias, AtlasEntry, Face, Glpyh, MAX_PAGES,
+                                    PAGE_SIZE};
+use crate::font_atlas::packer::Skyline;
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "hermitshell atlas 5";
+
+pub struct CachedAtlas {
+    // texels of each page as the texture stores them, PAGE_SIZE square
+    pub pages: Vec<Vec<u8>>,
+    pub skylines: Vec<Skyline>,
+    pub lookup: HashMap<(Face, Glpyh), AtlasEntry>,
+}
+
+fn color_type(antialias: Antialias) -> image::ColorType {
+    match antialias {
+        Antialias::Grayscale => return image::ColorType::L8,
+        Antialias::Lcd => return image::ColorType::Rgba8,
+    }
+}
+
+// where the atlas for this font chain and settings lives
+// None if the platform has no cache directory
+pub fn atlas_dir(fonts: &[Vec<u8>], settings: &str) -> Option<PathBuf> {
+    let mut hasher = Sha256::new();
+    for font_data in fonts {
+        hasher.update((font_data.len() as u64).to_le_bytes());
+        hasher.update(font_data);
+    }
+    hasher.update(settings.as_bytes());
+    let hash: String = hasher.finalize().iter()
+        .map(|byte| format!("{:02x}", byte)).collect();
+    return Some(dirs::cache_dir()?.join("hermitshell").join("atlas").join(hash));
+}
+
+pub fn save(dir: &Path, atlas: &CachedAtlas, antialias: Antialias)
+    -> io::Result<()> {
+    // written beside the old one then swapped in so a crash
+    // never leaves half an atlas behind
+    let tmp = dir.with_extension(format!("tmp{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(&tmp)?;
+
+    let mut table = format!("{}\n", HEADER);
+    for (idx, (pixels, skyline)) in atlas.pages.iter()
+        .zip(&atlas.skylines).enumerate() {
+        image::save_buffer_with_format(tmp.join(format!("page_{}.png", idx)),
+                                       pixels,
+                                       PAGE_SIZE as u32, PAGE_SIZE as u32,
+                                       color_type(antialias),
+                                       image::ImageFormat::Png)
+            .map_err(io::Error::other)?;
+
+        table.push_str("page");
+        for (x, y, width) in skyline.segments() {
+            table.push_str(&format!(" {} {} {}", x, y, width));
+        }
+        table.push('\n');
+    }
+    for ((face, glpyh), entry) in atlas.lookup.iter()
+        .filter(|(_, entry)| !entry.color) {
+        let (kind, number) = match *glpyh {
+            Glpyh::Char(c) => ("glpyh", c as u32),
+            Glpyh::Id(id) => ("id", id as u32),
+            Glpyh::Cluster(_) => continue,
+        };
+        table.push_str(&format!("{} {} {} {} {} {} {} {} {} {}\n", kind,
+                                *face as u32, number,
+                                entry.size.0, entry.size.1,
+                                entry.pos.0, entry.pos.1, entry.page,
+                                entry.bearing.0, entry.bearing.1));
+    }
+    fs::write(tmp.join("lookup"), table)?;
+
+    let _ = fs::remove_dir_all(dir);
+    return fs::rename(&tmp, dir);
+}
+
+pub fn load(dir: &Path, antialias: Antialias) -> Option<CachedAtlas> {
+    let table = fs::read_to_string(dir.join("lookup")).ok()?;
+    let mut lines = table.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+
+    let mut cached = CachedAtlas { pages: Vec::new(), skylines: Vec::new(),
+                                   lookup: HashMap::new() };
+    for line in lines {
+        let mut words = line.split(' ');
+        let kind = words.next()?;
+        // only the bearings can be negative
+        let numbers: Vec<i64> = words.map(|word| word.parse().ok())
+            .collect::<Option<_>>()?;
+        let unsigned = |idx: usize| u64::try_from(numbers[idx]).ok();
+        match kind {
+            "page" => {
+                if !numbers.len().is_multiple_of(3) {
+                    return None;
+                }
+                let segments: Vec<(u64, u64, u64)> = (0..numbers.len()).step_by(3)
+                    .map(|idx| Some((unsigned(idx)?, unsigned(idx + 1)?,
+                                     unsigned(idx + 2)?)))
+                    .collect::<Option<_>>()?;
+                cached.skylines.push(
+                    Skyline::from_segments(PAGE_SIZE, PAGE_SIZE, &segments)?);
+
+                let page = image::open(
+                    dir.join(format!("page_{}.png", cached.pages.len()))).ok()?;
+                if (page.width(), page.height())
+                    != (PAGE_SIZE as u32, PAGE_SIZE as u32) {
+                    return None;
+                }
+                cached.pages.push(match antialias {
+                    Antialias::Grayscale => page.into_luma8().into_raw(),
+                    Antialias::Lcd => page.into_rgba8().into_raw(),
+                });
+            }
+            "glpyh" | "id" => {
+                if numbers.len() != 9 {
+                    return None;
+                }
+                let face = *Face::ALL.get(usize::try_from(numbers[0]).ok()?)?;
+                let glpyh = match kind {
+                    "glpyh" => Glpyh::Char(
+                        char::from_u32(u32::try_from(numbers[1]).ok()?)?),
+                    _ => Glpyh::Id(u16::try_from(numbers[1]).ok()?),
+                };
+                let (width, height) = (unsigned(2)?, unsigned(3)?);
+                let (x, y, page) = (unsigned(4)?, unsigned(5)?, unsigned(6)?);
+                let bearing = (i32::try_from(numbers[7]).ok()?,
+                               i32::try_from(numbers[8]).ok()?);
+                // every glpyh has to be inside a page we have
+                if page >= cached.pages.len() as u64
+                    || x.saturating_add(width) > PAGE_SIZE
+                    || y.saturating_add(height) > PAGE_SIZE {
+                    return None;
+                }
+                cached.lookup.insert((face, glpyh),
+                    AtlasEntry::new((width, height), (x, y), page as u32, bearing));
+            }
+            _ => return None,
+        }
+    }
+
+    if cached.pages.is_empty() || cached.pages.len() > MAX_PAGES as usize {
+        return None;
+    }
+    return Some(cached);
+}
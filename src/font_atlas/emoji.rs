@@ -0,0 +1,500 @@
+/*
+ *  colour glpyhs, mostly emoji, that fontdue can't draw
+ *
+ *  bitmap glpyhs (CBDT and sbix pngs) are scaled from the nearest strike,
+ *  COLR glpyhs are painted layer by layer with tiny-skia. either way the
+ *  result is straight alpha srgb rgba for the colour page of the atlas
+ *
+ *  a colour glpyh is sized to the font like any other but never wider
+ *  than the two cells an emoji takes
+ */
+use fontdue::Metrics;
+use tiny_skia::{BlendMode, Color, FillRule, FilterQuality, GradientStop, Mask, Path,
+                PathBuilder, Pixmap, PixmapPaint, Point, Rect, Shader, SpreadMode,
+                Transform};
+use ttf_parser::colr::{ClipBox, ColorStop, CompositeMode, GradientExtend, Paint, Painter,
+                       SweepGradient};
+use ttf_parser::{GlyphId, RasterGlyphImage, RasterImageFormat, RgbaColor};
+
+// colour of COLR layers drawn in the text colour, which isn't known
+// when the glpyh is cached
+const FOREGROUND: RgbaColor = RgbaColor { red: 255, green: 255, blue: 255, alpha: 255 };
+
+enum Source<'a> {
+    Bitmap(RasterGlyphImage<'a>),
+    // glpyh id and the box it is painted in, in font units
+    Layers(GlyphId, ClipBox),
+}
+
+// where a glpyh lands, in pixels from the pen position on the baseline
+struct Placement {
+    // pixels per unit of the source, font units or bitmap pixels
+    scale: f32,
+    left: i32,
+    bottom: i32,
+    width: usize,
+    height: usize,
+    advance: f32,
+}
+
+// sweep gradients are painted as this many wedges of solid colour
+const SWEEP_STEPS: usize = 90;
+
+// a font with colour glpyhs, checked once when it is loaded
+pub struct ColorFont {
+    data: Box<[u8]>,
+}
+
+impl ColorFont {
+    // None if it isn't a font or has no colour glpyhs, only those
+    // are copied
+    pub fn parse(font_data: &[u8]) -> Option<Self> {
+        let face = ttf_parser::Face::parse(font_data, 0).ok()?;
+        if !has_color_tables(&face) {
+            return None;
+        }
+        return Some(Self { data: Box::from(font_data) });
+    }
+
+    // parsing only reads the table directory, the tables are read in place
+    fn face(&self) -> Option<ttf_parser::Face<'_>> {
+        return ttf_parser::Face::parse(&self.data, 0).ok();
+    }
+}
+
+// the font has glpyhs fontdue can't draw
+fn has_color_tables(face: &ttf_parser::Face) -> bool {
+    let tables = face.tables();
+    return tables.colr.is_some() || tables.cbdt.is_some() || tables.sbix.is_some();
+}
+
+fn source<'a>(face: &'a ttf_parser::Face<'a>, c: char, font_size: f32)
+    -> Option<(GlyphId, Source<'a>)> {
+    let id = face.glyph_index(c)?;
+    if let Some(image) = face.glyph_raster_image(id, font_size.round() as u16)
+        .filter(|image| image.format == RasterImageFormat::PNG) {
+        return Some((id, Source::Bitmap(image)));
+    }
+    if !face.is_color_glyph(id) {
+        return None;
+    }
+
+    // variable fonts are drawn at their default instance
+    let bounds = face.tables().colr.and_then(|colr| colr.clip_box(id, &[]))
+        .or_else(|| face.glyph_bounding_box(id).map(|rect| ClipBox {
+            x_min: rect.x_min as f32, y_min: rect.y_min as f32,
+            x_max: rect.x_max as f32, y_max: rect.y_max as f32 }))
+        .unwrap_or(ClipBox {
+            x_min: 0.0, y_min: face.descender() as f32,
+            x_max: face.glyph_hor_advance(id).unwrap_or(0) as f32,
+            y_max: face.ascender() as f32 });
+    return Some((id, Source::Layers(id, bounds)));
+}
+
+fn placement(face: &ttf_parser::Face, id: GlyphId, source: &Source, font_size: f32,
+             max_width: f32) -> Placement {
+    let units = font_size / face.units_per_em() as f32;
+    let advance = (face.glyph_hor_advance(id).unwrap_or(0) as f32 * units)
+        .min(max_width);
+    match source {
+        Source::Bitmap(image) => {
+            let scale = (font_size / image.pixels_per_em as f32)
+                .min(max_width / image.width.max(1) as f32);
+            return Placement {
+                scale,
+                left: (image.x as f32 * scale).round() as i32,
+                bottom: (image.y as f32 * scale).round() as i32,
+                width: ((image.width as f32 * scale).round() as usize).max(1),
+                height: ((image.height as f32 * scale).round() as usize).max(1),
+                advance,
+            };
+        }
+        Source::Layers(_, bounds) => {
+            let scale = units.min(max_width / (bounds.x_max - bounds.x_min).max(1.0));
+            let (left, bottom) = ((bounds.x_min * scale).floor() as i32,
+                                  (bounds.y_min * scale).floor() as i32);
+            return Placement {
+                scale,
+                left,
+                bottom,
+                width: ((bounds.x_max * scale).ceil() as i32 - left).max(0) as usize,
+                height: ((bounds.y_max * scale).ceil() as i32 - bottom).max(0) as usize,
+                advance,
+            };
+        }
+    }
+}
+
+fn metrics_of(placement: &Placement) -> Metrics {
+    return Metrics { xmin: placement.left, ymin: placement.bottom,
+                     width: placement.width, height: placement.height,
+                     advance_width: placement.advance, ..Default::default() };
+}
+
+// metrics of a char as rasterize will draw it, None if the font
+// has no colour glpyh for it
+pub fn metrics(font: &ColorFont, c: char, font_size: f32, max_width: f32)
+    -> Option<Metrics> {
+    let face = font.face()?;
+    let (id, source) = source(&face, c, font_size)?;
+    return Some(metrics_of(&placement(&face, id, &source, font_size, max_width)));
+}
+
+// straight alpha srgb rgba of a char, None if the font has no colour
+// glpyh for it or it can't be drawn
+pub fn rasterize(font: &ColorFont, c: char, font_size: f32, max_width: f32)
+    -> Option<(Metrics, Vec<u8>)> {
+    let face = font.face()?;
+    let (id, source) = source(&face, c, font_size)?;
+    let placement = placement(&face, id, &source, font_size, max_width);
+    let metrics = metrics_of(&placement);
+    if placement.width == 0 || placement.height == 0 {
+        return Some((metrics, Vec::new()));
+    }
+
+    let mut pixmap = Pixmap::new(placement.width as u32, placement.height as u32)?;
+    match source {
+        Source::Bitmap(image) => {
+            let bitmap = Pixmap::decode_png(image.data).ok()?;
+            let paint = PixmapPaint { quality: FilterQuality::Bicubic,
+                                      ..Default::default() };
+            pixmap.draw_pixmap(0, 0, bitmap.as_ref(), &paint,
+                Transform::from_scale(
+                    placement.width as f32 / bitmap.width() as f32,
+                    placement.height as f32 / bitmap.height() as f32),
+                None);
+        }
+        Source::Layers(id, _) => {
+            // font units are y up, the pixmap is y down from its top left
+            let base = Transform::from_row(placement.scale, 0.0, 0.0, -placement.scale,
+                -placement.left as f32,
+                (placement.bottom + placement.height as i32) as f32);
+            let mut canvas = Canvas { face: &face, layers: vec![(pixmap, None)],
+                                      clips: Vec::new(), transforms: vec![base],
+                                      outline: None };
+            face.paint_color_glyph(id, 0, FOREGROUND, &mut canvas)?;
+            pixmap = canvas.layers.swap_remove(0).0;
+        }
+    }
+
+    let pixels = pixmap.pixels().iter().flat_map(|pixel| {
+        let pixel = pixel.demultiply();
+        return [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()];
+    }).collect();
+    return Some((metrics, pixels));
+}
+
+struct Outline(PathBuilder);
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+// paints COLR layers into a pixmap, paths are kept in pixels
+struct Canvas<'a, 'f> {
+    face: &'f ttf_parser::Face<'a>,
+    // layers being drawn and how each goes onto the one below,
+    // the first is the glpyh
+    layers: Vec<(Pixmap, Option<BlendMode>)>,
+    clips: Vec<Mask>,
+    // font units to pixels, the last is the current one
+    transforms: Vec<Transform>,
+    // the last glpyh outlined
+    outline: Option<Path>,
+}
+
+impl Canvas<'_, '_> {
+    fn transform(&self) -> Transform {
+        return self.transforms.last().copied().unwrap_or_default();
+    }
+
+    fn pixmap(&mut self) -> &mut Pixmap {
+        return &mut self.layers.last_mut().expect("the glpyh layer is never popped").0;
+    }
+
+    // the current clip narrowed to a path, no path clips everything
+    fn push_clip_path(&mut self, path: Option<Path>) {
+        let (width, height) = (self.layers[0].0.width(), self.layers[0].0.height());
+        let mut mask = match self.clips.last() {
+            Some(mask) => mask.clone(),
+            None => {
+                let Some(mut mask) = Mask::new(width, height) else { return };
+                mask.data_mut().fill(255);
+                mask
+            }
+        };
+        match path {
+            Some(path) => mask.intersect_path(&path, FillRule::Winding, true,
+                                              Transform::identity()),
+            None => mask.data_mut().fill(0),
+        }
+        self.clips.push(mask);
+    }
+
+    fn shader(&self, paint: Paint) -> Option<Shader<'static>> {
+        let transform = self.transform();
+        match paint {
+            Paint::Solid(color) => return Some(Shader::SolidColor(self::color(color))),
+            Paint::LinearGradient(gradient) => {
+                // the gradient runs from p0 towards p1 with lines of equal
+                // colour parallel to p0 p2, so it ends where p1 is projected
+                // onto the normal of p0 p2
+                let normal = (gradient.y2 - gradient.y0, gradient.x0 - gradient.x2);
+                let length = normal.0 * normal.0 + normal.1 * normal.1;
+                let (dx, dy) = (gradient.x1 - gradient.x0, gradient.y1 - gradient.y0);
+                let end = if length > 0.0 {
+                    let along = (dx * normal.0 + dy * normal.1) / length;
+                    Point::from_xy(gradient.x0 + normal.0 * along,
+                                   gradient.y0 + normal.1 * along)
+                } else {
+                    Point::from_xy(gradient.x1, gradient.y1)
+                };
+                let stops = gradient.stops(0, &[])
+                    .map(|stop| GradientStop::new(stop.stop_offset, self::color(stop.color)))
+                    .collect();
+                return tiny_skia::LinearGradient::new(
+                    Point::from_xy(gradient.x0, gradient.y0), end, stops,
+                    spread(gradient.extend), transform);
+            }
+            Paint::RadialGradient(gradient) => {
+                // tiny-skia's start circle is a point, a bigger one is
+                // made by squeezing the stops past its radius
+                let inner = if gradient.r1 > gradient.r0 && gradient.r0 > 0.0 {
+                    gradient.r0 / gradient.r1
+                } else {
+                    0.0
+                };
+                let stops = gradient.stops(0, &[])
+                    .map(|stop| GradientStop::new(inner + stop.stop_offset * (1.0 - inner),
+                                                  self::color(stop.color)))
+                    .collect();
+                return tiny_skia::RadialGradient::new(
+                    Point::from_xy(gradient.x0, gradient.y0),
+                    Point::from_xy(gradient.x1, gradient.y1),
+                    gradient.r1, stops, spread(gradient.extend), transform);
+            }
+            // tiny-skia has no sweep gradients, paint_sweep draws them
+            Paint::SweepGradient(_) => return None,
+        }
+    }
+
+    // where paint goes, everything in the clip once one is set
+    // otherwise the outline
+    fn paint_area(&self) -> Option<Path> {
+        match self.clips.last() {
+            Some(_) => return Rect::from_xywh(0.0, 0.0, self.layers[0].0.width() as f32,
+                                              self.layers[0].0.height() as f32)
+                .map(PathBuilder::from_rect),
+            None => return self.outline.clone(),
+        }
+    }
+
+    // tiny-skia has no sweep gradient shader so the area is cut into wedges
+    // around the centre, each filled with the colour at its middle
+    fn paint_sweep(&mut self, gradient: SweepGradient) {
+        let mut stops: Vec<ColorStop> = gradient.stops(0, &[]).collect();
+        if stops.is_empty() {
+            return;
+        }
+        stops.sort_by(|a, b| a.stop_offset.total_cmp(&b.stop_offset));
+
+        // the wedges are drawn aliased through a mask of the area so their
+        // edges don't leave seams
+        let mask = match self.clips.last() {
+            Some(mask) => mask.clone(),
+            None => {
+                let Some(outline) = &self.outline else { return };
+                let (width, height) = (self.layers[0].0.width(), self.layers[0].0.height());
+                let Some(mut mask) = Mask::new(width, height) else { return };
+                mask.fill_path(outline, FillRule::Winding, true, Transform::identity());
+                mask
+            }
+        };
+
+        // angles are in half turns counter clockwise from +x
+        let (start, end) = (gradient.start_angle * 180.0, gradient.end_angle * 180.0);
+        let radius = 8.0 * self.face.units_per_em() as f32;
+        let transform = self.transform();
+        let step = 360.0 / SWEEP_STEPS as f32;
+        for idx in 0..SWEEP_STEPS {
+            let (from, to) = (idx as f32 * step, (idx + 1) as f32 * step);
+            let middle = (from + to) / 2.0;
+            let t = if end != start { (middle - start) / (end - start) } else { 0.0 };
+            let color = color_at(&stops, extend(t, gradient.extend));
+
+            let mut wedge = PathBuilder::new();
+            wedge.move_to(gradient.center_x, gradient.center_y);
+            for angle in [from, to] {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                wedge.line_to(gradient.center_x + radius * cos,
+                              gradient.center_y + radius * sin);
+            }
+            wedge.close();
+            let Some(wedge) = wedge.finish().and_then(|path| path.transform(transform))
+                else { continue };
+            let paint = tiny_skia::Paint { shader: Shader::SolidColor(self::color(color)),
+                                           anti_alias: false, ..Default::default() };
+            self.pixmap().fill_path(&wedge, &paint, FillRule::Winding,
+                                    Transform::identity(), Some(&mask));
+        }
+    }
+}
+
+impl<'a> Painter<'a> for Canvas<'a, '_> {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        let mut outline = Outline(PathBuilder::new());
+        self.outline = self.face.outline_glyph(glyph_id, &mut outline)
+            .and_then(|_| outline.0.finish())
+            .and_then(|path| path.transform(self.transform()));
+    }
+
+    // fills the outline, or everything in the clip once one is set
+    fn paint(&mut self, paint: Paint<'a>) {
+        if let Paint::SweepGradient(gradient) = paint {
+            self.paint_sweep(gradient);
+            return;
+        }
+        let Some(shader) = self.shader(paint) else { return };
+        let paint = tiny_skia::Paint { shader, anti_alias: true, ..Default::default() };
+        let Some(path) = self.paint_area() else { return };
+        let mask = self.clips.last().cloned();
+        self.pixmap().fill_path(&path, &paint, FillRule::Winding, Transform::identity(),
+                                mask.as_ref());
+    }
+
+    fn push_clip(&mut self) {
+        self.push_clip_path(self.outline.clone());
+    }
+
+    fn push_clip_box(&mut self, clipbox: ClipBox) {
+        let path = Rect::from_ltrb(clipbox.x_min, clipbox.y_min,
+                                   clipbox.x_max, clipbox.y_max)
+            .map(PathBuilder::from_rect)
+            .and_then(|path| path.transform(self.transform()));
+        self.push_clip_path(path);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn push_layer(&mut self, mode: CompositeMode) {
+        let (width, height) = (self.layers[0].0.width(), self.layers[0].0.height());
+        if let Some(pixmap) = Pixmap::new(width, height) {
+            self.layers.push((pixmap, Some(blend_mode(mode))));
+        }
+    }
+
+    fn pop_layer(&mut self) {
+        if self.layers.len() < 2 {
+            return;
+        }
+        let Some((layer, Some(blend_mode))) = self.layers.pop() else { return };
+        let paint = PixmapPaint { blend_mode, ..Default::default() };
+        self.pixmap().draw_pixmap(0, 0, layer.as_ref(), &paint, Transform::identity(),
+                                  None);
+    }
+
+    fn push_transform(&mut self, transform: ttf_parser::Transform) {
+        let transform = self.transform().pre_concat(Transform::from_row(
+            transform.a, transform.b, transform.c, transform.d,
+            transform.e, transform.f));
+        self.transforms.push(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        if self.transforms.len() > 1 {
+            self.transforms.pop();
+        }
+    }
+}
+
+fn color(color: RgbaColor) -> Color {
+    return Color::from_rgba8(color.red, color.green, color.blue, color.alpha);
+}
+
+// colour along a gradient with its stops in order, t from 0 to 1
+fn color_at(stops: &[ColorStop], t: f32) -> RgbaColor {
+    let after = stops.iter().position(|stop| stop.stop_offset > t);
+    let (before, after) = match after {
+        Some(0) => return stops[0].color,
+        Some(idx) => (stops[idx - 1], stops[idx]),
+        None => return stops[stops.len() - 1].color,
+    };
+    let along = (t - before.stop_offset) / (after.stop_offset - before.stop_offset);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * along).round() as u8;
+    let (a, b) = (before.color, after.color);
+    return RgbaColor { red: mix(a.red, b.red), green: mix(a.green, b.green),
+                       blue: mix(a.blue, b.blue), alpha: mix(a.alpha, b.alpha) };
+}
+
+// t past the ends of a gradient brought back onto it
+fn extend(t: f32, extend: GradientExtend) -> f32 {
+    match extend {
+        GradientExtend::Pad => return t.clamp(0.0, 1.0),
+        GradientExtend::Repeat => return t - t.floor(),
+        GradientExtend::Reflect => {
+            let t = t.rem_euclid(2.0);
+            return if t > 1.0 { 2.0 - t } else { t };
+        }
+    }
+}
+
+fn spread(extend: GradientExtend) -> SpreadMode {
+    match extend {
+        GradientExtend::Pad => return SpreadMode::Pad,
+        GradientExtend::Repeat => return SpreadMode::Repeat,
+        GradientExtend::Reflect => return SpreadMode::Reflect,
+    }
+}
+
+fn blend_mode(mode: CompositeMode) -> BlendMode {
+    match mode {
+        CompositeMode::Clear => return BlendMode::Clear,
+        CompositeMode::Source => return BlendMode::Source,
+        CompositeMode::Destination => return BlendMode::Destination,
+        CompositeMode::SourceOver => return BlendMode::SourceOver,
+        CompositeMode::DestinationOver => return BlendMode::DestinationOver,
+        CompositeMode::SourceIn => return BlendMode::SourceIn,
+        CompositeMode::DestinationIn => return BlendMode::DestinationIn,
+        CompositeMode::SourceOut => return BlendMode::SourceOut,
+        CompositeMode::DestinationOut => return BlendMode::DestinationOut,
+        CompositeMode::SourceAtop => return BlendMode::SourceAtop,
+        CompositeMode::DestinationAtop => return BlendMode::DestinationAtop,
+        CompositeMode::Xor => return BlendMode::Xor,
+        CompositeMode::Plus => return BlendMode::Plus,
+        CompositeMode::Screen => return BlendMode::Screen,
+        CompositeMode::Overlay => return BlendMode::Overlay,
+        CompositeMode::Darken => return BlendMode::Darken,
+        CompositeMode::Lighten => return BlendMode::Lighten,
+        CompositeMode::ColorDodge => return BlendMode::ColorDodge,
+        CompositeMode::ColorBurn => return BlendMode::ColorBurn,
+        CompositeMode::HardLight => return BlendMode::HardLight,
+        CompositeMode::SoftLight => return BlendMode::SoftLight,
+        CompositeMode::Difference => return BlendMode::Difference,
+        CompositeMode::Exclusion => return BlendMode::Exclusion,
+        CompositeMode::Multiply => return BlendMode::Multiply,
+        CompositeMode::Hue => return BlendMode::Hue,
+        CompositeMode::Saturation => return BlendMode::Saturation,
+        CompositeMode::Color => return BlendMode::Color,
+        CompositeMode::Luminosity => return BlendMode::Luminosity,
+    }
+}
@@ -1,10 +1,60 @@
-use crate::font_atlas::packer::packer;
+/*
+ *  glpyh atlas filled on demand
+ *
+ *  glpyhs are rasterized the first time they are drawn and packed into
+ *  pages of a texture array with a skyline per page. when every page is
+ *  full a new layer is added up to MAX_PAGES, after that the page with the
+ *  fewest glpyhs on screen, then the one drawn least recently, is emptied
+ *  and reused.
+ *
+ *  eviction is lru by page rather than by glpyh, a skyline can't give back
+ *  the space of a single glpyh so a page is only ever emptied whole, and
+ *  every glpyh in it goes.
+ *
+ *  generation goes up whenever uvs or the texture change so the renderer
+ *  knows to rebind and rebuild what it has uploaded. glpyhs on screen that
+ *  were evicted are rasterized again by that rebuild in the same frame, a
+ *  page is only emptied once a frame so that always settles. only a screen
+ *  needing more than MAX_PAGES of glpyhs at once goes without some of them.
+ *
+ *  each glpyh comes from the first font in the chain that has it, glpyhs
+ *  none of them have are drawn as a hollow box (tofu)
+ *
+ *  glpyhs are keyed by face and char. bold, italic and bold italic use
+ *  their own font when one is given and has the glpyh, otherwise the
+ *  closest one is made bolder or slanted, see synthetic.rs
+ *
+ *  shaped text is keyed by glpyh id instead, ids are always drawn with
+ *  the shaping font of the face so they mean the same as in shaper.rs
+ *
+ *  a char with combining marks that doesn't compose to a single char is
+ *  drawn as one glpyh made from the char and each mark, these are only
+ *  kept for the session
+ *
+ *  box drawing and block chars aren't taken from a font, they are drawn
+ *  the size of the cell so lines join, see box_drawing.rs
+ *
+ *  chars from a font in the chain with colour glpyhs (emoji) go in a
+ *  separate rgba page instead, see emoji.rs. when it fills up it is
+ *  emptied whole, at most once a frame, and it isn't saved
+ *
+ *  the atlas is kept on disk between runs, see cache.rs
+ */
+use crate::font_atlas::cache::{self, CachedAtlas};
 use crate::font_atlas::packer::Point;
-use crate::font_atlas::packer::BBox;
-use crate::font_atlas::packer::area_protect;
+use crate::font_atlas::packer::Skyline;
+use crate::font_atlas::synthetic;
+use crate::font_atlas::emoji;
+use crate::font_atlas::box_drawing;
+#[cfg(feature = "shaping")]
+use crate::font_atlas::shaper::Shaper;
 
 use core::slice::SlicePattern;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use unicode_normalization::UnicodeNormalization;
+#[cfg(feature = "shaping")]
+use std::rc::Rc;
 use wgpu::CommandEncoderDescriptor;
 use wgpu::BufferDescriptor;
 use wgpu::Extent3d;
@@ -16,237 +66,1027 @@ use wgpu::TextureDescriptor;
 
 #[derive(Clone)]
 pub struct TermConfig{
-    pub font_dir: String,
-    pub font_size: f32
+    // font files in the order glpyhs are looked for, the first sets
+    // the cell size
+    pub font_dirs: Vec<String>,
+    // fonts for the other faces, faked from the regular chain when None
+    pub bold_font: Option<String>,
+    pub italic_font: Option<String>,
+    pub bold_italic_font: Option<String>,
+    pub font_size: f32,
+    // max lines of history kept
+    pub scrollback_lines: usize,
+    pub antialias: Antialias,
+    // coverage is raised to 1 / gamma before blending, 1.0 leaves it linear
+    pub gamma: f32,
+    // pushes partial coverage towards solid, 0.0 is off
+    pub contrast: f32,
+    // multiplies the font's line height, 1.0 leaves it as is
+    pub line_height: f32,
+    // pixels added across each cell, can be negative
+    pub letter_spacing: f32,
+}
+
+// how glpyh edges are smoothed, either way the shader blends
+// the fg over the bg in linear space
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Antialias {
+    // one coverage value per pixel
+    Grayscale,
+    // coverage for each of r, g and b on a horizontal rgb lcd
+    Lcd,
+}
+
+impl Antialias {
+    // coverage is linear so the textures aren't srgb
+    pub fn format(self) -> TextureFormat {
+        match self {
+            Antialias::Grayscale => return TextureFormat::R8Unorm,
+            Antialias::Lcd => return TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> u64 {
+        match self {
+            Antialias::Grayscale => return 1,
+            Antialias::Lcd => return 4,
+        }
+    }
+}
+
+// size of a grid cell and where glpyhs sit in it, in pixels. every glpyh
+// is placed by its bearing from the same origin so they share a baseline
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CellMetrics {
+    pub width: f32,
+    pub height: f32,
+    // from the primary font, y up from the baseline so descent is
+    // negative
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    // (x, y) pixels from the top left of the cell to the pen position on
+    // the baseline, whole pixels so glpyh texels line up with the screen
+    pub origin: (f32, f32),
+}
+
+impl CellMetrics {
+    // the width is the advance of 'M' and the height the font's line,
+    // then line_height and letter_spacing are applied. space added or
+    // taken away is split evenly either side of the glpyhs
+    pub fn new(font: &fontdue::Font, font_size: f32, line_height: f32,
+               letter_spacing: f32) -> Self {
+        let advance = font.metrics('M', font_size).advance_width.ceil();
+        let (ascent, descent, line_gap) = font.horizontal_line_metrics(font_size)
+            .map(|line_metrics| (line_metrics.ascent, line_metrics.descent,
+                                 line_metrics.line_gap))
+            .unwrap_or((font_size, 0.0, 0.0));
+
+        let width = (advance + letter_spacing).round().max(1.0);
+        let height = ((ascent - descent + line_gap) * line_height).ceil().max(1.0);
+        let x = ((width - advance) / 2.0).floor();
+        let y = ((height - ascent + descent) / 2.0 + ascent).round();
+        return Self { width, height, ascent, descent, line_gap, origin: (x, y) };
+    }
+}
+
+// style of a glpyh, from the bold and italic cell attributes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Face {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl Face {
+    pub const ALL: [Face; 4] = [Face::Regular, Face::Bold, Face::Italic,
+                                Face::BoldItalic];
+
+    pub fn new(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => return Face::Regular,
+            (true, false) => return Face::Bold,
+            (false, true) => return Face::Italic,
+            (true, true) => return Face::BoldItalic,
+        }
+    }
+
+    pub fn bold(self) -> bool {
+        return matches!(self, Face::Bold | Face::BoldItalic);
+    }
+
+    pub fn italic(self) -> bool {
+        return matches!(self, Face::Italic | Face::BoldItalic);
+    }
+
+    // faces whose own font can draw this one, best first,
+    // anything they don't cover comes from the regular chain
+    fn sources(self) -> &'static [Face] {
+        match self {
+            Face::Regular => return &[],
+            Face::Bold => return &[Face::Bold],
+            Face::Italic => return &[Face::Italic],
+            Face::BoldItalic => return &[Face::BoldItalic, Face::Bold, Face::Italic],
+        }
+    }
+
+    // face whose font shapes and draws glpyh ids for this one, out of the
+    // faces that have a font. Regular is the primary font
+    pub fn shaping_source<T>(self, fonts: &HashMap<Face, T>) -> Face {
+        return self.sources().iter().copied()
+            .find(|source| fonts.contains_key(source))
+            .unwrap_or(Face::Regular);
+    }
+}
+
+// what a glpyh in the atlas was rasterized from
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Glpyh {
+    // looked up through the fallback chain
+    Char(char),
+    // from shaping, an id in the face's shaping font
+    Id(u16),
+    // a char and its combining marks, an index into FontAtlas::clusters
+    Cluster(u32),
+}
+
+// width and height of a page in pixels
+pub const PAGE_SIZE: u64 = 1024;
+// pages allocated before glpyhs start being evicted
+pub const MAX_PAGES: u32 = 4;
+// gap left right and below each glpyh so neighbours never touch
+const PADDING: u64 = 1;
+
+#[derive(Clone, Copy)]
+pub struct AtlasEntry {
+    // (w, h), glpyhs with nothing to draw are (0, 0)
+    pub size: Point,
+    // (x, y) in the page
+    pub pos: Point,
+    pub page: u32,
+    // (xmin, ymin) pixels from the pen position on the baseline to the
+    // bottom left of the glpyh, from whichever font it came from
+    pub bearing: (i32, i32),
+    // in the colour page rather than a coverage page
+    pub color: bool,
+    // frame the glpyh was last drawn in
+    last_used: u64,
+}
+
+impl AtlasEntry {
+    pub fn new(size: Point, pos: Point, page: u32, bearing: (i32, i32)) -> Self {
+        return Self { size, pos, page, bearing, color: false, last_used: 0 };
+    }
 }
 
 pub struct FontAtlas {
+    // a layer for each page
     pub atlas : wgpu::Texture,
-    // point = (u64, u64) => ((w, h), (x, y))
-    pub lookup : HashMap<char, (Point, Point)>,
+    pub lookup : HashMap<(Face, Glpyh), AtlasEntry>,
+    // size of a page
     pub atlas_size : Point,
+    // the fallback chain, fonts[0] is the primary font
+    pub fonts : Vec<fontdue::Font>,
+    // each font in the chain that has colour glpyhs, parsed for emoji
+    color_fonts: Vec<Option<emoji::ColorFont>>,
+    // straight alpha srgb glpyhs from color_fonts
+    pub color_atlas: wgpu::Texture,
+    color_page: Skyline,
+    // frame the colour page was last emptied in
+    color_cleared: u64,
+    // fonts given for the other faces
+    faces: HashMap<Face, fontdue::Font>,
+    #[cfg(feature = "shaping")]
+    pub shaper: Shaper,
+    // text of each Glpyh::Cluster
+    clusters: Vec<String>,
+    cluster_ids: HashMap<String, u32>,
+    // a cluster can only be measured by drawing it, so its metrics are
+    // kept from the first time it is drawn
+    cluster_metrics: HashMap<(Face, Glpyh), fontdue::Metrics>,
+    pub font_size: f32,
+    pub cell_metrics: CellMetrics,
+    pub antialias: Antialias,
+    // free space in each page
+    pages: Vec<Skyline>,
+    // frame each page was last emptied in
+    cleared: Vec<u64>,
+    pub generation: u64,
+    frame: u64,
+    // where the atlas is saved, None if there is nowhere to put it
+    pub cache_dir: Option<PathBuf>,
+    // glpyhs were added since it was loaded or saved
+    changed: bool,
 }
 
 impl FontAtlas {
-    
-    // creates the font texture atlas given a vector of rasterized glpyhs
-    // and positions of where those glpyhs are using a wpu::Buffer
-    // device is locked so need reference
-    fn font_atlas(pixels_boxes: &mut Vec<(Vec<u8>, (BBox, (u64, u64)))>, 
-                  device: &mut wgpu::Device, queue: &mut wgpu::Queue, size: (u64, u64)) -> 
-        wgpu::Texture {
-
-        let enc = device.create_command_encoder(
-            &CommandEncoderDescriptor { label: Some("font_atlas_enc") });
-
-        let font_atlas_tex = device.create_texture(
-            &TextureDescriptor { 
-                label: Some("font_atlas_tex"), 
+
+    // texture array with a layer for each page
+    fn page_texture(device: &wgpu::Device, pages: u32,
+                    antialias: Antialias) -> wgpu::Texture {
+        #[cfg(debug_assertions)]
+        {
+            println!("atlas pages: {} of ({}, {})", pages, PAGE_SIZE, PAGE_SIZE);
+        }
+
+        return device.create_texture(
+            &TextureDescriptor {
+                label: Some("font_atlas_tex"),
                 size: Extent3d{
-                    width: size.0 as u32,
-                    height: size.1 as u32,
-                    depth_or_array_layers: 1
-                }, 
-                mip_level_count: 1, 
-                sample_count: 1, 
+                    width: PAGE_SIZE as u32,
+                    height: PAGE_SIZE as u32,
+                    depth_or_array_layers: pages
+                },
+                mip_level_count: 1,
+                sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Bgra8UnormSrgb, 
-                usage: TextureUsages::RENDER_ATTACHMENT |
+                format: antialias.format(),
+                usage: TextureUsages::TEXTURE_BINDING |
                    TextureUsages::COPY_SRC |
                    TextureUsages::COPY_DST,
-                view_formats: &[wgpu::TextureFormat::Bgra8UnormSrgb],
+                view_formats: &[],
             }
         );
+    }
 
-        #[cfg(debug_assertions)]
-        {
-            println!("Size of atlas: ({}, {})", size.0, size.1);
+    fn color_texture(device: &wgpu::Device) -> wgpu::Texture {
+        return device.create_texture(
+            &TextureDescriptor {
+                label: Some("font_atlas_color_tex"),
+                size: Extent3d{
+                    width: PAGE_SIZE as u32,
+                    height: PAGE_SIZE as u32,
+                    depth_or_array_layers: 1
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING |
+                   TextureUsages::COPY_DST,
+                view_formats: &[],
+            }
+        );
+    }
+
+    // everything that changes the pixels of a glpyh, part of the cache key
+    // faces lists the fonts given for the other faces, in the order their
+    // data follows the chain
+    fn raster_settings(font_size: f32, antialias: Antialias,
+                       faces: &[Face], cell: CellMetrics) -> String {
+        return format!("size {} page {} padding {} {:?} faces {:?} slant {} embolden {} cell {:?}",
+                       font_size, PAGE_SIZE, PADDING, antialias, faces,
+                       synthetic::SLANT, synthetic::embolden_strength(font_size), cell);
+    }
+
+
+    fn read_font(path: &str) -> Result<(Vec<u8>, fontdue::Font), String> {
+        let font_data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let face = fontdue::Font::from_bytes(font_data.as_slice(),
+                                             fontdue::FontSettings::default())?;
+        return Ok((font_data, face));
+    }
+
+    // creates a new FontAtlas struct, starting from the cached atlas
+    // for these fonts if there is one, otherwise with one empty page
+    // fails if the primary font can't be loaded
+    pub fn new(term_config: TermConfig, device: &wgpu::Device,
+               queue: &wgpu::Queue) -> Result<Self, String> {
+        let font_size = term_config.font_size;
+        let antialias = term_config.antialias;
+
+        // read fonts from file and load data into abstraction
+        // the primary font has to load, fallbacks that don't are skipped
+        if term_config.font_dirs.is_empty() {
+            return Err("no font given".to_string());
+        }
+        let mut font_datas = Vec::new();
+        let mut fonts = Vec::new();
+        for (idx, data) in term_config.font_dirs.iter().enumerate() {
+            match Self::read_font(data) {
+                Ok((font_data, face)) => {
+                    font_datas.push(font_data);
+                    fonts.push(face);
+                }
+                Err(e) if idx == 0 => return Err(format!("can't load font {}: {}", data, e)),
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    println!("skipping fallback font {}: {}", data, _e);
+                }
+            }
+        }
+
+        // faces that don't load are faked like ones that weren't given
+        let mut faces = HashMap::new();
+        let mut given = Vec::new();
+        for (face, path) in [(Face::Bold, &term_config.bold_font),
+                             (Face::Italic, &term_config.italic_font),
+                             (Face::BoldItalic, &term_config.bold_italic_font)] {
+            let Some(path) = path else { continue };
+            match Self::read_font(path) {
+                Ok((font_data, font)) => {
+                    font_datas.push(font_data);
+                    faces.insert(face, font);
+                    given.push(face);
+                }
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    println!("faking {:?} face, {}: {}", face, path, _e);
+                }
+            }
         }
 
-        for (pixel_box_data, (pixels_bbox, pos_pixels)) in pixels_boxes { 
-             #[cfg(debug_assertions)]
-            {
-             println!("pixel_bbox size ({},{}) pos: ({},{})", 
-                      pixels_bbox.width, pixels_bbox.height, pos_pixels.0, pos_pixels.1);
+        #[cfg(feature = "shaping")]
+        let shaper = {
+            let mut datas: HashMap<Face, Rc<[u8]>> = HashMap::new();
+            datas.insert(Face::Regular, Rc::from(font_datas[0].as_slice()));
+            for (face, font_data) in given.iter().zip(&font_datas[fonts.len()..]) {
+                datas.insert(*face, Rc::from(font_data.as_slice()));
+            }
+            let mut shaping = HashMap::new();
+            for face in Face::ALL {
+                shaping.insert(face, Rc::clone(&datas[&face.shaping_source(&faces)]));
             }
+            Shaper::new(shaping, font_size)
+        };
+
+        let cell_metrics = CellMetrics::new(&fonts[0], font_size,
+            term_config.line_height, term_config.letter_spacing);
+        let cache_dir = cache::atlas_dir(&font_datas,
+            &Self::raster_settings(font_size, antialias, &given, cell_metrics));
+        let cached = cache_dir.as_deref()
+            .and_then(|dir| cache::load(dir, antialias))
+            .unwrap_or_else(|| CachedAtlas {
+                pages: Vec::new(),
+                skylines: vec![Skyline::new(PAGE_SIZE, PAGE_SIZE)],
+                lookup: HashMap::new(),
+            });
+
+        #[cfg(debug_assertions)]
+        println!("atlas cache {:?} has {} glpyhs", cache_dir,
+                 cached.lookup.len());
+
+        let atlas = Self::page_texture(device, cached.skylines.len() as u32,
+                                       antialias);
+        let bytes_per_pixel = antialias.bytes_per_pixel();
+        for (page, pixels) in cached.pages.iter().enumerate() {
             queue.write_texture(
-                 wgpu::ImageCopyTextureBase {
-                   texture: &font_atlas_tex,
-                   mip_level: 0,
-                   origin: wgpu::Origin3d {
-                       x: pos_pixels.0 as u32,
-                       y: pos_pixels.1 as u32,
-                       z: 0
-                   },
-                   aspect: wgpu::TextureAspect::All
-               }, 
-               &pixel_box_data.as_slice(), 
-               wgpu::ImageDataLayout { 
-                   offset: 0, 
-                   bytes_per_row: Some(pixels_bbox.width as u32 * 4), 
-                   rows_per_image: Some(pixels_bbox.height as u32) 
-               },
-               wgpu::Extent3d{
-                    width: pixels_bbox.width as u32,
-                    height: pixels_bbox.height as u32,
+                wgpu::ImageCopyTextureBase {
+                    texture: &atlas,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: page as u32 },
+                    aspect: wgpu::TextureAspect::All
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some((PAGE_SIZE * bytes_per_pixel) as u32),
+                    rows_per_image: Some(PAGE_SIZE as u32)
+                },
+                Extent3d {
+                    width: PAGE_SIZE as u32,
+                    height: PAGE_SIZE as u32,
                     depth_or_array_layers: 1
                 }
             );
         }
-        // submit queue with empty command buffer to write to gpu
+
+        let color_fonts = font_datas[..fonts.len()].iter()
+            .map(|font_data| emoji::ColorFont::parse(font_data))
+            .collect();
+
+        return Ok(Self{atlas,
+            lookup: cached.lookup,
+            atlas_size: (PAGE_SIZE, PAGE_SIZE),
+            fonts,
+            color_fonts,
+            color_atlas: Self::color_texture(device),
+            color_page: Skyline::new(PAGE_SIZE, PAGE_SIZE),
+            color_cleared: 0,
+            faces,
+            #[cfg(feature = "shaping")]
+            shaper,
+            clusters: Vec::new(),
+            cluster_ids: HashMap::new(),
+            cluster_metrics: HashMap::new(),
+            font_size,
+            cell_metrics,
+            antialias,
+            cleared: vec![0; cached.skylines.len()],
+            pages: cached.skylines,
+            generation: 0,
+            frame: 1,
+            cache_dir,
+            changed: false});
+    }
+
+    // writes the atlas to the cache directory if glpyhs were added
+    pub async fn save_cache(&mut self, device: &wgpu::Device,
+                            queue: &wgpu::Queue) -> std::io::Result<()> {
+        let Some(dir) = &self.cache_dir else { return Ok(()) };
+        if !self.changed {
+            return Ok(());
+        }
+
+        // read every page back from the gpu
+        let layers = self.pages.len() as u32;
+        let row_bytes = PAGE_SIZE * self.antialias.bytes_per_pixel();
+        let page_bytes = row_bytes * PAGE_SIZE;
+        let buf = device.create_buffer(&BufferDescriptor{
+            label: Some("font_atlas_cache_buf"),
+            size: page_bytes * layers as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(
+            &CommandEncoderDescriptor { label: Some("font_atlas_cache_enc") });
+        encoder.copy_texture_to_buffer(
+            self.atlas.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buf,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row_bytes as u32),
+                    rows_per_image: Some(PAGE_SIZE as u32)
+                }
+            },
+            Extent3d {
+                width: PAGE_SIZE as u32,
+                height: PAGE_SIZE as u32,
+                depth_or_array_layers: layers
+            });
         use std::iter;
-        queue.submit(iter::once(enc.finish()));
+        queue.submit(iter::once(encoder.finish()));
+
+        let slice = buf.slice(..);
+        let (sender, reciver) =
+            futures_intrusive::channel::shared::oneshot_channel();
+        slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
         device.poll(wgpu::Maintain::Wait);
+        match reciver.receive().await {
+            Some(Ok(())) => {}
+            Some(Err(e)) => return Err(std::io::Error::other(e)),
+            None => return Err(std::io::Error::other("atlas readback dropped")),
+        }
+
+        let pages = slice.get_mapped_range().chunks(page_bytes as usize)
+            .map(|page| page.to_vec()).collect();
+        buf.unmap();
 
-        return font_atlas_tex;
+        cache::save(dir, &CachedAtlas { pages, skylines: self.pages.clone(),
+                                        lookup: self.lookup.clone() },
+                    self.antialias)?;
+        #[cfg(debug_assertions)]
+        println!("saved {} glpyhs to atlas cache {:?}", self.lookup.len(), dir);
+
+        self.changed = false;
+        return Ok(());
+    }
+
+    // glpyhs looked up after this count as used in a new frame
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
     }
 
-    // creates a new FontAtlas struct
-    pub fn new(term_config: TermConfig, device: &mut wgpu::Device,
-               queue: &mut wgpu::Queue)
-        -> Self {
+    // where a glpyh is and its uv rect (u, v, width, height),
+    // rasterizing it if it isn't in the atlas yet
+    pub fn glpyh(&mut self, glpyh: Glpyh, face: Face, device: &wgpu::Device,
+                 queue: &wgpu::Queue) -> Option<(AtlasEntry, [f32; 4])> {
+        if !self.lookup.contains_key(&(face, glpyh)) {
+            self.load(glpyh, face, device, queue);
+        }
 
-        let data = term_config.font_dir;
-        let font_size = term_config.font_size;
+        let frame = self.frame;
+        let entry = self.lookup.get_mut(&(face, glpyh))?;
+        entry.last_used = frame;
+        if entry.size.0 == 0 || entry.size.1 == 0 {
+            return None;
+        }
+        let entry = *entry;
+        return Some((entry, self.uv_rect(entry.size, entry.pos)));
+    }
 
-        // read font from file and load data into abstraction
-        let font_data = std::fs::read(data).unwrap();
-        let face = fontdue::Font::from_bytes(font_data.as_slice(), 
-                                     fontdue::FontSettings::default()).unwrap();
+    // glpyh for a char followed by combining marks, the composed char
+    // when there is one
+    pub fn cluster(&mut self, text: &str) -> Glpyh {
+        let composed: String = text.nfc().collect();
+        let mut chars = composed.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Glpyh::Char(c);
+        }
 
-        // find raster data and bboxes
-        let mut pixels : Vec<(char, Vec<u8>)> = Vec::new();
-        let mut bboxes = Vec::new();
-        for (&glyph_c, &id) in face.chars() {
-            // convert id -> u16 
-            let (metrics, glyph) = 
-                face.rasterize_indexed_subpixel(id.into(), font_size);
-                        // use px 
+        if let Some(&idx) = self.cluster_ids.get(&composed) {
+            return Glpyh::Cluster(idx);
+        }
+        let idx = self.clusters.len() as u32;
+        self.clusters.push(composed.clone());
+        self.cluster_ids.insert(composed, idx);
+        return Glpyh::Cluster(idx);
+    }
 
-            #[cfg(debug_assertions)]
-            {
-                let _ = 
-                image::save_buffer_with_format(format!("pure_glpyh_{}.png", glyph_c), 
-                                               &(glyph.clone()), metrics.width as u32, metrics.height as u32, 
-                                               image::ColorType::Rgb8, image::ImageFormat::Png);
+    fn uv_rect(&self, size: Point, pos: Point) -> [f32; 4] {
+        let (atlas_width, atlas_height) =
+            (self.atlas_size.0 as f32, self.atlas_size.1 as f32);
+        return [pos.0 as f32 / atlas_width, pos.1 as f32 / atlas_height,
+                size.0 as f32 / atlas_width, size.1 as f32 / atlas_height];
+    }
+
+    // font for a glpyh in a face and the face it actually draws,
+    // None if nothing has the glpyh
+    fn font_for(&self, glpyh: char, face: Face) -> Option<(&fontdue::Font, Face)> {
+        for source in face.sources() {
+            match self.faces.get(source) {
+                Some(font) if font.has_glyph(glpyh) => return Some((font, *source)),
+                _ => {}
             }
+        }
+        let font = self.fonts.iter().find(|font| font.has_glyph(glpyh))?;
+        return Some((font, Face::Regular));
+    }
 
-            // no alpha channel so we create ours with 255 init
-            let mut rgba = Vec::new();
-            for channels in glyph.chunks(3) {
-                let mut pixel = Vec::from(channels);
-                pixel.push(255); // alpha
-                rgba.append(&mut pixel);
+    // font, glpyh index and the face it draws for a glpyh in a face,
+    // None for the tofu box
+    fn source(&self, glpyh: Glpyh, face: Face)
+        -> Option<(&fontdue::Font, u16, Face)> {
+        match glpyh {
+            Glpyh::Char(c) => {
+                let (font, drawn) = self.font_for(c, face)?;
+                return Some((font, font.lookup_glyph_index(c), drawn));
             }
-            #[cfg(debug_assertions)]
-            {
-                println!("rgba len {}", rgba.len());
-                let _ = 
-                image::save_buffer_with_format(format!("alpha_glpyh_{}.png", glyph_c), 
-                                               &rgba.clone(), metrics.width as u32, metrics.height as u32, 
-                                               image::ColorType::Rgba8, image::ImageFormat::Png);
+            Glpyh::Id(id) => {
+                let drawn = face.shaping_source(&self.faces);
+                let font = self.faces.get(&drawn).unwrap_or(&self.fonts[0]);
+                if id >= font.glyph_count() {
+                    return None;
+                }
+                return Some((font, id, drawn));
             }
+            Glpyh::Cluster(_) => return None,
+        }
+    }
 
-            // convert rgba to bgra
-            let mut bgra: Vec<u8> = Vec::new();
-            for channel in rgba.chunks(4) {
-                let mut bgra_chunk = Vec::from(channel);
-                bgra_chunk.reverse();
-                bgra.append(&mut bgra_chunk);
+    // metrics of a glpyh as rasterize will draw it, without drawing it
+    // other than clusters which are drawn the first time they're measured
+    pub fn metrics(&mut self, glpyh: Glpyh, face: Face) -> fontdue::Metrics {
+        if let Glpyh::Cluster(_) = glpyh {
+            if let Some(metrics) = self.cluster_metrics.get(&(face, glpyh)) {
+                return *metrics;
+            }
+            let metrics = self.rasterize(glpyh, face).0;
+            self.cluster_metrics.insert((face, glpyh), metrics);
+            return metrics;
+        }
+        if let Some(metrics) = self.box_metrics(glpyh) {
+            return metrics;
+        }
+        if let Some((color_font, c)) = self.color_source(glpyh, face) {
+            if let Some(metrics) = emoji::metrics(color_font, c, self.font_size,
+                                                  2.0 * self.cell_advance()) {
+                return metrics;
             }
-            
-            // push pixel data 
-            // null char has problems with encoding
-            if  !(metrics.width == 0 || metrics.height == 0 || glyph_c == '\0') {
-                pixels.push((glyph_c, bgra));
-                bboxes.push(BBox { glpyh: glyph_c, 
-                    width: metrics.width as u64,
-                    height: metrics.height as u64 });
-                
+        }
+        let Some((font, index, drawn)) = self.source(glpyh, face)
+            else { return self.tofu_metrics() };
 
-                #[cfg(debug_assertions)]
-                println!("w {} h {} char {}", 
-                         metrics.width, metrics.height, glyph_c)
+        let mut metrics = font.metrics_indexed(index, self.font_size);
+        if face.bold() && !drawn.bold() {
+            metrics = synthetic::emboldened(metrics,
+                synthetic::embolden_strength(self.font_size));
+        }
+        if face.italic() && !drawn.italic() {
+            metrics = synthetic::obliqued(metrics, synthetic::SLANT);
+        }
+        return metrics;
+    }
+
+    // rasterizes with the best font for the face, faking whatever it lacks,
+    // or the tofu box. pixels are in the atlas format
+    fn rasterize(&self, glpyh: Glpyh, face: Face) -> (fontdue::Metrics, Vec<u8>) {
+        if let Glpyh::Cluster(idx) = glpyh {
+            return self.rasterize_cluster(&self.clusters[idx as usize], face);
+        }
+        if let Some(drawn) = self.rasterize_box(glpyh) {
+            return drawn;
+        }
+        let Some((font, index, drawn)) = self.source(glpyh, face)
+            else { return self.tofu() };
+
+        let (mut metrics, mut pixels) = self.rasterize_with(font, index);
+        let bytes_per_pixel = self.antialias.bytes_per_pixel() as usize;
+        if face.bold() && !drawn.bold() {
+            (metrics, pixels) = synthetic::embolden(metrics, &pixels,
+                bytes_per_pixel, synthetic::embolden_strength(self.font_size));
+        }
+        if face.italic() && !drawn.italic() {
+            (metrics, pixels) = synthetic::oblique(metrics, &pixels,
+                bytes_per_pixel, synthetic::SLANT);
+        }
+        return (metrics, pixels);
+    }
+
+    // draws the first char then each mark over it into one glpyh. marks
+    // with no advance sit after the char like in proportional fonts,
+    // ones with an advance are drawn over their own cell like in
+    // monospace fonts
+    fn rasterize_cluster(&self, text: &str, face: Face)
+        -> (fontdue::Metrics, Vec<u8>) {
+        // each part and the pixels right of the char's origin it is drawn at
+        let mut parts = Vec::new();
+        let mut base_advance = 0;
+        for (idx, c) in text.chars().enumerate() {
+            let (metrics, pixels) = self.rasterize(Glpyh::Char(c), face);
+            let pen = if idx == 0 {
+                base_advance = metrics.advance_width.round() as i32;
+                0
+            } else if metrics.advance_width > 0.0 {
+                0
+            } else {
+                base_advance
+            };
+            if metrics.width > 0 && metrics.height > 0 {
+                parts.push((metrics, pixels, pen));
             }
         }
+        let Some(first) = parts.first().map(|part| part.0)
+            else { return (fontdue::Metrics::default(), Vec::new()) };
 
-        // pos_boxes is not in order  
-        let (size, pos_boxes) = packer(&mut bboxes);
+        // bounds of all the parts, y up from the baseline
+        let left = parts.iter().map(|(m, _, pen)| pen + m.xmin).min().unwrap_or(0);
+        let right = parts.iter().map(|(m, _, pen)| pen + m.xmin + m.width as i32)
+            .max().unwrap_or(0);
+        let bottom = parts.iter().map(|(m, _, _)| m.ymin).min().unwrap_or(0);
+        let top = parts.iter().map(|(m, _, _)| m.ymin + m.height as i32)
+            .max().unwrap_or(0);
+        let (width, height) = ((right - left) as usize, (top - bottom) as usize);
 
-        // remove None types
-        let pos_boxes : Vec<(BBox, (u64, u64))> = 
-            pos_boxes.into_iter().
-            filter(|(_, pos)| 
-                   area_protect(pos.1) * 
-                   area_protect(pos.0) != 0)
-            .collect();
+        let bytes_per_pixel = self.antialias.bytes_per_pixel() as usize;
+        let mut out = vec![0u8; width * height * bytes_per_pixel];
+        for (metrics, pixels, pen) in &parts {
+            let x = (pen + metrics.xmin - left) as usize;
+            let y = (top - metrics.ymin - metrics.height as i32) as usize;
+            let row_bytes = metrics.width * bytes_per_pixel;
+            for (row, line) in pixels.chunks(row_bytes).enumerate() {
+                let at = ((y + row) * width + x) * bytes_per_pixel;
+                for (dst, src) in out[at..at + row_bytes].iter_mut().zip(line) {
+                    *dst = (*dst).max(*src);
+                }
+            }
+        }
+
+        return (fontdue::Metrics { xmin: left, ymin: bottom, width, height,
+                                   ..first }, out);
+    }
 
-        let mut atlas_lookup = HashMap::new(); 
-        for boxes in pos_boxes.clone() {
-            atlas_lookup.insert(boxes.0.glpyh,
-                                ((boxes.0.width, boxes.0.height), 
-                                 boxes.1));
+    // colour font and char for a glpyh that comes from one
+    fn color_source(&self, glpyh: Glpyh, face: Face) -> Option<(&emoji::ColorFont, char)> {
+        let Glpyh::Char(c) = glpyh else { return None };
+        if box_drawing::is_procedural(c) {
+            return None;
         }
+        let (font, _) = self.font_for(c, face)?;
+        let idx = self.fonts.iter().position(|chain| std::ptr::eq(chain, font))?;
+        return Some((self.color_fonts[idx].as_ref()?, c));
+    }
+
+    // straight alpha srgb rgba of a colour glpyh, None if the glpyh isn't
+    // one. it can take up both cells of a wide char
+    fn rasterize_color(&self, glpyh: Glpyh, face: Face)
+        -> Option<(fontdue::Metrics, Vec<u8>)> {
+        let (color_font, c) = self.color_source(glpyh, face)?;
+        return emoji::rasterize(color_font, c, self.font_size, 2.0 * self.cell_advance());
+    }
 
-        let mut pixels_boxes : Vec<(Vec<u8>, (BBox, (u64, u64)))> = Vec::new();
-        for (glpyh, data) in pixels { 
-            let mut position : Vec<(BBox, (u64, u64))> =
-                pos_boxes.clone().into_iter().filter(|(bbox, _pos)| bbox.glpyh == glpyh).collect(); 
-            pixels_boxes.push((data, position.pop().unwrap()));            
+    // coverage of a glpyh from one font in the atlas format
+    fn rasterize_with(&self, font: &fontdue::Font, index: u16)
+        -> (fontdue::Metrics, Vec<u8>) {
+        match self.antialias {
+            Antialias::Grayscale => return font.rasterize_indexed(index, self.font_size),
+            Antialias::Lcd => {
+                let (metrics, rgb) =
+                    font.rasterize_indexed_subpixel(index, self.font_size);
+                // alpha is unused, it keeps the overall coverage
+                let mut rgba: Vec<u8> = Vec::with_capacity(rgb.len() / 3 * 4);
+                for pixel in rgb.chunks(3) {
+                    let alpha = pixel[0].max(pixel[1]).max(pixel[2]);
+                    rgba.extend_from_slice(&[pixel[0], pixel[1], pixel[2], alpha]);
+                }
+                return (metrics, rgba);
+            }
         }
+    }
 
-        // create atlas texutre set up as image tex
-        let atlas = Self::font_atlas(&mut pixels_boxes, 
-                                     device, queue, size);
+    // pixels across a cell
+    fn cell_advance(&self) -> f32 {
+        return self.cell_metrics.width;
+    }
 
+    // metrics of a glpyh drawn to fill its cell, the bearing takes it
+    // from the origin back to the top left of the cell. None if the
+    // glpyh comes from a font
+    fn box_metrics(&self, glpyh: Glpyh) -> Option<fontdue::Metrics> {
+        let Glpyh::Char(c) = glpyh else { return None };
+        if !box_drawing::is_procedural(c) {
+            return None;
+        }
+        let CellMetrics { width, height, origin: (x, y), .. } = self.cell_metrics;
+        return Some(fontdue::Metrics { xmin: -x as i32, ymin: y as i32 - height as i32,
+            width: width as usize, height: height as usize, advance_width: width,
+            ..Default::default() });
+    }
 
-        return Self{atlas, 
-            lookup : atlas_lookup, atlas_size : size}; 
+    // coverage of a box drawing or block char in the atlas format, the
+    // same for every face
+    fn rasterize_box(&self, glpyh: Glpyh) -> Option<(fontdue::Metrics, Vec<u8>)> {
+        let metrics = self.box_metrics(glpyh)?;
+        let Glpyh::Char(c) = glpyh else { return None };
+        let coverage = box_drawing::rasterize(c, metrics.width, metrics.height)?;
+        match self.antialias {
+            Antialias::Grayscale => return Some((metrics, coverage)),
+            Antialias::Lcd => return Some((metrics,
+                coverage.iter().flat_map(|&c| [c, c, c, c]).collect())),
+        }
+    }
+
+    // hollow box the width of a cell and about as tall as a capital,
+    // sat on the baseline
+    fn tofu_metrics(&self) -> fontdue::Metrics {
+        let advance = self.cell_advance() as usize;
+        let ascent = self.cell_metrics.ascent;
+
+        let inset = (advance / 8).max(1);
+        let width = advance.saturating_sub(2 * inset).max(3);
+        let height = ((ascent * 0.8).round() as usize).max(3);
+        let xmin = inset as i32 - self.cell_metrics.origin.0 as i32;
+        return fontdue::Metrics { xmin, ymin: 0, width, height,
+            advance_width: advance as f32, ..Default::default() };
     }
 
+    fn tofu(&self) -> (fontdue::Metrics, Vec<u8>) {
+        let metrics = self.tofu_metrics();
+        let (width, height) = (metrics.width, metrics.height);
+        let stroke = ((self.font_size / 16.0).round() as usize).max(1);
+
+        let mut coverage = vec![0u8; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                if x < stroke || y < stroke
+                    || x + stroke >= width || y + stroke >= height {
+                    coverage[y * width + x] = 255;
+                }
+            }
+        }
+
+        match self.antialias {
+            Antialias::Grayscale => return (metrics, coverage),
+            Antialias::Lcd => return (metrics,
+                coverage.iter().flat_map(|&c| [c, c, c, c]).collect()),
+        }
+    }
+
+    // rasterizes a glpyh and writes it into a free spot
+    // nothing is added if there is no room left this frame
+    fn load(&mut self, glpyh: Glpyh, face: Face, device: &wgpu::Device,
+            queue: &wgpu::Queue) {
+        if let Some((metrics, pixels)) = self.rasterize_color(glpyh, face) {
+            self.load_color(glpyh, face, metrics, &pixels, queue);
+            return;
+        }
+        let (metrics, pixels) = self.rasterize(glpyh, face);
+        if let Glpyh::Cluster(_) = glpyh {
+            self.cluster_metrics.insert((face, glpyh), metrics);
+        }
+        let size = (metrics.width as u64, metrics.height as u64);
+        let bearing = (metrics.xmin, metrics.ymin);
+
+        // null char has problems with encoding
+        let empty = size.0 == 0 || size.1 == 0 || glpyh == Glpyh::Char('\0');
+        if empty || size.0 + PADDING > PAGE_SIZE || size.1 + PADDING > PAGE_SIZE {
+            self.lookup.insert((face, glpyh), AtlasEntry { size: (0, 0), pos: (0, 0),
+                page: 0, bearing, color: false, last_used: self.frame });
+            return;
+        }
+
+        let Some((page, pos)) = self.allocate(size, device, queue) else {
+            #[cfg(debug_assertions)]
+            println!("no room in the atlas for {:?}", glpyh);
+            return;
+        };
+
+        #[cfg(debug_assertions)]
+        println!("w {} h {} {:?} {:?} page {} pos ({}, {})",
+                 size.0, size.1, glpyh, face, page, pos.0, pos.1);
+
+        queue.write_texture(
+             wgpu::ImageCopyTextureBase {
+               texture: &self.atlas,
+               mip_level: 0,
+               origin: wgpu::Origin3d {
+                   x: pos.0 as u32,
+                   y: pos.1 as u32,
+                   z: page
+               },
+               aspect: wgpu::TextureAspect::All
+           },
+           pixels.as_slice(),
+           wgpu::ImageDataLayout {
+               offset: 0,
+               bytes_per_row: Some((size.0 * self.antialias.bytes_per_pixel()) as u32),
+               rows_per_image: Some(size.1 as u32)
+           },
+           wgpu::Extent3d{
+                width: size.0 as u32,
+                height: size.1 as u32,
+                depth_or_array_layers: 1
+            }
+        );
+
+        self.lookup.insert((face, glpyh), AtlasEntry { size, pos, page, bearing,
+            color: false, last_used: self.frame });
+        self.changed = true;
+    }
+
+    // writes a colour glpyh into the colour page, emptying it if there is
+    // no room and it wasn't already emptied this frame
+    fn load_color(&mut self, glpyh: Glpyh, face: Face, metrics: fontdue::Metrics,
+                  pixels: &[u8], queue: &wgpu::Queue) {
+        let size = (metrics.width as u64, metrics.height as u64);
+        let bearing = (metrics.xmin, metrics.ymin);
+        if size.0 == 0 || size.1 == 0
+            || size.0 + PADDING > PAGE_SIZE || size.1 + PADDING > PAGE_SIZE {
+            self.lookup.insert((face, glpyh), AtlasEntry { size: (0, 0), pos: (0, 0),
+                page: 0, bearing, color: false, last_used: self.frame });
+            return;
+        }
+
+        let (width, height) = (size.0 + PADDING, size.1 + PADDING);
+        let pos = match self.color_page.insert(width, height) {
+            Some(pos) => pos,
+            None if self.color_cleared != self.frame => {
+                #[cfg(debug_assertions)]
+                println!("emptying the colour page");
+
+                self.lookup.retain(|_, entry| !entry.color);
+                self.color_page.clear();
+                self.color_cleared = self.frame;
+                self.generation += 1;
+                let Some(pos) = self.color_page.insert(width, height) else { return };
+                pos
+            }
+            None => {
+                #[cfg(debug_assertions)]
+                println!("no room in the colour page for {:?}", glpyh);
+                return;
+            }
+        };
+
+        #[cfg(debug_assertions)]
+        println!("colour w {} h {} {:?} {:?} pos ({}, {})",
+                 size.0, size.1, glpyh, face, pos.0, pos.1);
+
+        queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &self.color_atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: pos.0 as u32, y: pos.1 as u32, z: 0 },
+                aspect: wgpu::TextureAspect::All
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.0 as u32 * 4),
+                rows_per_image: Some(size.1 as u32)
+            },
+            wgpu::Extent3d {
+                width: size.0 as u32,
+                height: size.1 as u32,
+                depth_or_array_layers: 1
+            }
+        );
+
+        self.lookup.insert((face, glpyh), AtlasEntry { size, pos, page: 0, bearing,
+            color: true, last_used: self.frame });
+    }
+
+    // finds space for a glpyh, adding or reusing a page when all are full
+    fn allocate(&mut self, size: Point, device: &wgpu::Device,
+                queue: &wgpu::Queue) -> Option<(u32, Point)> {
+        let (width, height) = (size.0 + PADDING, size.1 + PADDING);
+        for (page, skyline) in self.pages.iter_mut().enumerate() {
+            if let Some(pos) = skyline.insert(width, height) {
+                return Some((page as u32, pos));
+            }
+        }
+
+        let page = if (self.pages.len() as u32) < MAX_PAGES {
+            self.grow(device, queue);
+            self.pages.len() - 1
+        } else {
+            self.evict()?
+        };
+        let pos = self.pages[page].insert(width, height)?;
+        return Some((page as u32, pos));
+    }
+
+    // adds a layer, the old layers are copied over so uvs stay the same
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let layers = self.pages.len() as u32;
+        let atlas = Self::page_texture(device, layers + 1, self.antialias);
+
+        let mut encoder = device.create_command_encoder(
+            &CommandEncoderDescriptor { label: Some("font_atlas_grow_enc") });
+        encoder.copy_texture_to_texture(
+            self.atlas.as_image_copy(),
+            atlas.as_image_copy(),
+            Extent3d {
+                width: PAGE_SIZE as u32,
+                height: PAGE_SIZE as u32,
+                depth_or_array_layers: layers
+            });
+        // glpyhs written to the old texture land before the copy
+        use std::iter;
+        queue.submit(iter::once(encoder.finish()));
+
+        self.atlas = atlas;
+        self.pages.push(Skyline::new(PAGE_SIZE, PAGE_SIZE));
+        self.cleared.push(0);
+        self.generation += 1;
+    }
+
+    // empties the page with the fewest glpyhs drawn this frame,
+    // ties go to the one whose most recently drawn glpyh is the oldest
+    fn evict(&mut self) -> Option<usize> {
+        // (glpyhs drawn this frame, last drawn) for each page
+        let mut usage = vec![(0, 0); self.pages.len()];
+        for entry in self.lookup.values()
+            .filter(|entry| entry.size.0 > 0 && !entry.color) {
+            let (on_screen, newest) = &mut usage[entry.page as usize];
+            if entry.last_used == self.frame {
+                *on_screen += 1;
+            }
+            *newest = (*newest).max(entry.last_used);
+        }
+
+        let (page, _) = usage.iter().enumerate()
+            .filter(|(page, _)| self.cleared[*page] != self.frame)
+            .min_by_key(|(_, &usage)| usage)?;
+
+        #[cfg(debug_assertions)]
+        println!("evicting atlas page {}", page);
+
+        self.lookup.retain(|_, entry| {
+            return entry.size.0 == 0 || entry.color || entry.page != page as u32;
+        });
+        self.pages[page].clear();
+        self.cleared[page] = self.frame;
+        self.generation += 1;
+        return Some(page);
+    }
 
     // function to get glpyh data on a single char
     // returns wgpu::BufferSlice ready to be rendered as image data
-    pub async fn get_glpyh_data(&self, glpyh: char, 
+    pub async fn get_glpyh_data(&self, glpyh: char,
           device: &mut wgpu::Device, queue: &mut wgpu::Queue) -> wgpu::Buffer {
-            // if get position of char 
-            if let Some(position) = self.lookup.get(&glpyh) {
+            // if get position of char
+            if let Some(entry) = self.lookup.get(&(Face::Regular, Glpyh::Char(glpyh))) {
+                let position = (entry.size, entry.pos);
+                let bytes_per_pixel = self.antialias.bytes_per_pixel();
                 // create buffer for loading glpyh
                 let mut encoder = device.create_command_encoder(
                     &CommandEncoderDescriptor { label: Some("font_atlas_glpyh_enc") });
 
                 let buf = device.create_buffer(&BufferDescriptor{
                     label: Some(&format!("glpyh {} buf internal", glpyh)),
-                    size: position.0.1 * (4 * position.0.0).next_multiple_of(256),
+                    size: position.0.1 * (bytes_per_pixel * position.0.0).next_multiple_of(256),
                     usage: wgpu::BufferUsages::MAP_READ |
                             wgpu::BufferUsages::COPY_DST,
                     mapped_at_creation: false,
                 });
 
                 encoder.copy_texture_to_buffer(
-                    wgpu_types::ImageCopyTexture { 
-                        texture: &self.atlas, 
-                        mip_level: 0, 
+                    wgpu_types::ImageCopyTexture {
+                        texture: &self.atlas,
+                        mip_level: 0,
                         origin: wgpu::Origin3d{
                             x: position.1.0 as u32,
-                            y: position.1.1 as u32, 
-                            z: 0}, 
-                        aspect: wgpu_types::TextureAspect::All 
+                            y: position.1.1 as u32,
+                            z: entry.page},
+                        aspect: wgpu_types::TextureAspect::All
                     },
-                    wgpu_types::ImageCopyBuffer { 
-                        buffer: &buf, 
+                    wgpu_types::ImageCopyBuffer {
+                        buffer: &buf,
                         layout: wgpu_types::ImageDataLayout {
-                            offset: 0, 
-                            bytes_per_row: Some((position.0.0 as u32 * 4).next_multiple_of(256)),
+                            offset: 0,
+                            bytes_per_row: Some((position.0.0 as u32 * bytes_per_pixel as u32).next_multiple_of(256)),
                             rows_per_image: Some(position.0.1 as u32)
                         }
                     },
-                    Extent3d { 
-                        width: (position.0.0 as u32 * 4).next_multiple_of(256).div_ceil(4), 
-                        height: position.0.1 as u32, 
-                        depth_or_array_layers: 1 
+                    Extent3d {
+                        width: (position.0.0 as u32 * bytes_per_pixel as u32).next_multiple_of(256).div_ceil(bytes_per_pixel as u32),
+                        height: position.0.1 as u32,
+                        depth_or_array_layers: 1
                 });
-               
+
                 // submit to queue to write buf.
                 use std::iter;
                 queue.submit(iter::once(encoder.finish()));
@@ -255,10 +1095,9 @@ impl FontAtlas {
 
                 #[cfg(debug_assertions)]
                 {
-                    use image::Rgba;
                     let slice = buf.slice(..);
 
-                    let (sender, reciver) = 
+                    let (sender, reciver) =
                             futures_intrusive::channel::shared::oneshot_channel();
 
                     slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
@@ -266,19 +1105,17 @@ impl FontAtlas {
 
                     if let Some(Ok(())) = reciver.receive().await {
                         let buf_data = slice.get_mapped_range();
-                        let width = (position.0.0 as u32 * 4).next_multiple_of(256).div_ceil(4);
-                        match image::ImageBuffer::<Rgba<u8>, _>
-                            ::from_raw(width, position.0.1 as u32, buf_data.as_slice()) {
-                                Some(image) => match 
-                                    image.save(format!("glpyh_get_{}.png", &glpyh)) {
-                                        Ok(()) => println!("image get save succesful"),
-                                        Err(e) => println!("image get save unsuccesful , {}", e)
-                                    }
-                                None => {
-                                    println!(
-                                        "Image get buffer for glpyh {} unsuccesful", glpyh                                          )
-                                }
-                            }
+                        let width = (position.0.0 as u32 * bytes_per_pixel as u32).next_multiple_of(256).div_ceil(bytes_per_pixel as u32);
+                        let color_type = match self.antialias {
+                            Antialias::Grayscale => image::ColorType::L8,
+                            Antialias::Lcd => image::ColorType::Rgba8,
+                        };
+                        match image::save_buffer(format!("glpyh_get_{}.png", &glpyh),
+                                                 buf_data.as_slice(), width,
+                                                 position.0.1 as u32, color_type) {
+                            Ok(()) => println!("image get save succesful"),
+                            Err(e) => println!("image get save unsuccesful , {}", e)
+                        }
 
                     }
                 }
@@ -290,4 +1127,4 @@ impl FontAtlas {
         }
     }
 }
-             
+
@@ -2,5 +2,14 @@
 pub mod packer;
 #[path ="font_atlas.rs"]
 pub mod font_atlas;
-#[path="glpyh_loader.rs"]
-pub mod glpyh_loader;
+#[path ="cache.rs"]
+pub mod cache;
+#[path ="synthetic.rs"]
+pub mod synthetic;
+#[path ="emoji.rs"]
+pub mod emoji;
+#[path ="box_drawing.rs"]
+pub mod box_drawing;
+#[cfg(feature = "shaping")]
+#[path ="shaper.rs"]
+pub mod shaper;
@@ -0,0 +1,118 @@
+/*
+ *  text shaping for runs of cells, built with the shaping feature
+ *
+ *  a run of cells with the same attributes is shaped with rustybuzz so
+ *  ligatures and marks come out the way the font wants. the result is
+ *  glpyh ids in the face's shaping font, the same one the atlas draws
+ *  ids with, each tied to the char its cluster starts at so the caller
+ *  can keep them on the grid.
+ *
+ *  each shaping font is parsed once when the shaper is made. shaped runs
+ *  are cached by face and text, rows that come back unchanged aren't
+ *  shaped again
+ */
+use crate::font_atlas::font_atlas::Face;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// runs kept for each face before its cache is emptied
+const MAX_RUNS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlpyh {
+    // glpyh id in the shaping font, 0 when the font doesn't have the char
+    pub id: u16,
+    // byte offset in the text of the first char of the glpyh's cluster
+    pub cluster: usize,
+    // pixels right of where its cluster starts and up from the baseline
+    pub offset: (f32, f32),
+    // pixels the pen moves right after the glpyh
+    pub advance: f32,
+}
+
+pub struct Shaper {
+    // each shaping font parsed, None if it didn't parse
+    fonts: Vec<Option<rustybuzz::Face<'static>>>,
+    // index in fonts for each face, faces without their own font share one
+    font_of: [Option<usize>; 4],
+    font_size: f32,
+    // shaped runs for each face by text
+    runs: [HashMap<String, Rc<[ShapedGlpyh]>>; 4],
+}
+
+impl Shaper {
+    // fonts has the data of the shaping font for each face
+    pub fn new(fonts: HashMap<Face, Rc<[u8]>>, font_size: f32) -> Self {
+        let mut datas: Vec<&Rc<[u8]>> = Vec::new();
+        let mut parsed = Vec::new();
+        let mut font_of = [None; 4];
+        for face in Face::ALL {
+            let Some(data) = fonts.get(&face) else { continue };
+            let idx = match datas.iter().position(|other| Rc::ptr_eq(other, data)) {
+                Some(idx) => idx,
+                None => {
+                    // a parsed font borrows its data, which is kept for the
+                    // rest of the run. there is one shaper for each atlas
+                    let data_ref: &'static [u8] = Box::leak(Box::from(data.as_ref()));
+                    parsed.push(rustybuzz::Face::from_slice(data_ref, 0));
+                    datas.push(data);
+                    datas.len() - 1
+                }
+            };
+            font_of[face as usize] = Some(idx);
+        }
+        return Self { fonts: parsed, font_of, font_size, runs: Default::default() };
+    }
+
+    // glpyhs for a run of text in logical order, left to right
+    pub fn shape(&mut self, text: &str, face: Face) -> Rc<[ShapedGlpyh]> {
+        let runs = &mut self.runs[face as usize];
+        if let Some(shaped) = runs.get(text) {
+            return Rc::clone(shaped);
+        }
+
+        let font = self.font_of[face as usize].and_then(|idx| self.fonts[idx].as_ref());
+        let shaped: Rc<[ShapedGlpyh]> = match font {
+            Some(font) => Rc::from(Self::shape_with(font, self.font_size, text)),
+            None => Rc::from([]),
+        };
+
+        if runs.len() >= MAX_RUNS {
+            runs.clear();
+        }
+        runs.insert(text.to_string(), Rc::clone(&shaped));
+        return shaped;
+    }
+
+    fn shape_with(font: &rustybuzz::Face, font_size: f32, text: &str)
+        -> Vec<ShapedGlpyh> {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        // cells always run left to right, even for rtl text
+        buffer.set_direction(rustybuzz::Direction::LeftToRight);
+        let output = rustybuzz::shape(font, &[], buffer);
+
+        let scale = font_size / font.units_per_em() as f32;
+        let mut glpyhs = Vec::with_capacity(output.len());
+        // pen position in font units, and where the current cluster started
+        let (mut pen, mut cluster_pen) = (0, 0);
+        let mut cluster = None;
+        for (info, position) in output.glyph_infos().iter()
+            .zip(output.glyph_positions()) {
+            if cluster != Some(info.cluster) {
+                cluster = Some(info.cluster);
+                cluster_pen = pen;
+            }
+            glpyhs.push(ShapedGlpyh {
+                id: info.glyph_id as u16,
+                cluster: info.cluster as usize,
+                offset: ((pen - cluster_pen + position.x_offset) as f32 * scale,
+                         position.y_offset as f32 * scale),
+                advance: position.x_advance as f32 * scale,
+            });
+            pen += position.x_advance;
+        }
+        return glpyhs;
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ *  fake bold and italic for when a face isn't supplied
+ *
+ *  both work on rasterized coverage in the atlas format, bytes_per_pixel
+ *  bytes a pixel with every byte treated the same. the metrics are
+ *  widened to fit and the bearing moved so the glpyh stays on the
+ *  baseline. the advance is left alone since cells are a fixed width.
+ */
+use fontdue::Metrics;
+
+// horizontal shift for each pixel above the baseline, about 11 degrees
+pub const SLANT: f32 = 0.2;
+
+// pixels a stroke is thickened by at this font size
+pub fn embolden_strength(font_size: f32) -> usize {
+    return ((font_size / 24.0).round() as usize).max(1);
+}
+
+// metrics of a glpyh after embolden
+pub fn emboldened(metrics: Metrics, strength: usize) -> Metrics {
+    if metrics.width == 0 || metrics.height == 0 {
+        return metrics;
+    }
+    return Metrics { width: metrics.width + strength, ..metrics };
+}
+
+// smears each row right by strength pixels, keeping the brightest
+// coverage under each pixel
+pub fn embolden(metrics: Metrics, pixels: &[u8], bytes_per_pixel: usize,
+                strength: usize) -> (Metrics, Vec<u8>) {
+    if metrics.width == 0 || metrics.height == 0 {
+        return (metrics, pixels.to_vec());
+    }
+
+    let width = emboldened(metrics, strength).width;
+    let mut out = vec![0u8; width * metrics.height * bytes_per_pixel];
+    for y in 0..metrics.height {
+        for x in 0..metrics.width {
+            let src = (y * metrics.width + x) * bytes_per_pixel;
+            for shift in 0..=strength {
+                let dst = (y * width + x + shift) * bytes_per_pixel;
+                for byte in 0..bytes_per_pixel {
+                    out[dst + byte] = out[dst + byte].max(pixels[src + byte]);
+                }
+            }
+        }
+    }
+
+    return (Metrics { width, ..metrics }, out);
+}
+
+// pixels a row of a glpyh moves right by, row 0 is the top and the
+// shift is taken at the middle of the row
+fn row_shift(metrics: Metrics, slant: f32, row: usize) -> f32 {
+    let above = metrics.ymin + (metrics.height - 1 - row) as i32;
+    return (above as f32 + 0.5) * slant;
+}
+
+// metrics of a glpyh after oblique
+pub fn obliqued(metrics: Metrics, slant: f32) -> Metrics {
+    if metrics.width == 0 || metrics.height == 0 {
+        return metrics;
+    }
+    let (top, bottom) = (row_shift(metrics, slant, 0),
+                         row_shift(metrics, slant, metrics.height - 1));
+    let (left, right) = (top.min(bottom).floor(), top.max(bottom).ceil());
+    return Metrics { width: metrics.width + (right - left) as usize,
+                     xmin: metrics.xmin + left as i32, ..metrics };
+}
+
+// shears rows right in proportion to their height above the baseline,
+// rows below it go left. fractional shifts are split between two pixels
+pub fn oblique(metrics: Metrics, pixels: &[u8], bytes_per_pixel: usize,
+               slant: f32) -> (Metrics, Vec<u8>) {
+    if metrics.width == 0 || metrics.height == 0 {
+        return (metrics, pixels.to_vec());
+    }
+
+    let sheared = obliqued(metrics, slant);
+    let (width, left) = (sheared.width, (sheared.xmin - metrics.xmin) as f32);
+
+    let mut coverage = vec![0f32; width * metrics.height * bytes_per_pixel];
+    for y in 0..metrics.height {
+        let offset = row_shift(metrics, slant, y) - left;
+        let (whole, part) = (offset.floor() as usize, offset.fract());
+        for x in 0..metrics.width {
+            let src = (y * metrics.width + x) * bytes_per_pixel;
+            let dst = (y * width + x + whole) * bytes_per_pixel;
+            for byte in 0..bytes_per_pixel {
+                let value = pixels[src + byte] as f32;
+                coverage[dst + byte] += value * (1.0 - part);
+                if part > 0.0 {
+                    coverage[dst + bytes_per_pixel + byte] += value * part;
+                }
+            }
+        }
+    }
+
+    let out = coverage.iter()
+        .map(|&value| value.round().min(255.0) as u8).collect();
+    return (sheared, out);
+}
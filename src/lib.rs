@@ -7,44 +7,115 @@
 #![feature(slice_pattern)] 
 #![feature(allocator_api)]
 pub mod font_atlas;
-use font_atlas::font_atlas::TermConfig;
-use font_atlas::glpyh_loader::GlpyhLoader;
+pub mod terminal;
+#[cfg(feature = "shaping")]
+use font_atlas::box_drawing;
+use font_atlas::font_atlas::{Antialias, CellMetrics, Face, FontAtlas, Glpyh, TermConfig};
+use terminal::term::{CursorShape, Term};
+use terminal::color;
+use terminal::grid::{Cell, ATTR_BOLD, ATTR_HIDDEN, ATTR_ITALIC, ATTR_STRIKE,
+                     ATTR_UNDERLINE};
+use terminal::keys;
+use terminal::kitty::{self, KeyEventType};
 
-use wgpu::ImageDataLayout;
 use wgpu::TextureFormat;
-use wgpu::{include_wgsl, CommandEncoderDescriptor, RenderPipeline};
+use wgpu::{include_wgsl, RenderPipeline};
 use wgpu::util::DeviceExt;
 use winit::application::ApplicationHandler;
 use winit::{
     event::*,
     window::{Window, WindowId},
-    event_loop::ActiveEventLoop, 
-    keyboard::PhysicalKey,
-    keyboard::KeyCode,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoopProxy},
+    keyboard::{Key, ModifiersState, NamedKey},
+    platform::modifier_supplement::KeyEventExtModifierSupplement,
     dpi::PhysicalSize
 };
-use portable_pty::{native_pty_system, PtySize, CommandBuilder};
-use fontdue::layout::{CoordinateSystem, Layout, TextStyle};
+use portable_pty::{native_pty_system, PtySize, CommandBuilder, MasterPty};
 
 use std::borrow::BorrowMut;
-use std::{iter, io::Read, io::Write, alloc::Global, sync::{Arc, Mutex}};
-
-pub fn read_from_pty(reader: &mut Box<dyn Read + Send>) -> String {
-    // make buffer the same as a max data of the terminal
-    let mut u8_buf: [u8; 80*24] = [0; 80*24];
-    reader.read(&mut u8_buf).unwrap();
+use std::{iter, io::Read, io::Write, alloc::Global, sync::{Arc, Mutex}, thread};
+use std::time::{Duration, Instant};
+
+// events sent to the winit loop from other threads
+#[derive(Debug)]
+pub enum TermEvent {
+    PtyOutput(String),
+    // the shell closed the pty
+    PtyExit,
+}
 
-    // convert u8 buffer to string
-    let str_buf:String = String::from_utf8(u8_buf.to_vec()).unwrap();
+// decodes as much of pending as is valid utf-8
+// an incomplete sequence at the end is left in pending for the next read
+// invalid bytes are replaced so a bad byte can't stall the stream
+pub fn decode_utf8(pending: &mut Vec<u8>) -> String {
+    let mut decoded = String::new();
+    let mut start = 0;
+    loop {
+        match std::str::from_utf8(&pending[start..]) {
+            Ok(valid) => {
+                decoded.push_str(valid);
+                start = pending.len();
+                break;
+            }
+            Err(e) => {
+                let valid_up_to = start + e.valid_up_to();
+                decoded.push_str(
+                    std::str::from_utf8(&pending[start..valid_up_to]).unwrap());
+                match e.error_len() {
+                    Some(len) => {
+                        decoded.push(char::REPLACEMENT_CHARACTER);
+                        start = valid_up_to + len;
+                    }
+                    // sequence split across reads
+                    None => {
+                        start = valid_up_to;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    pending.drain(..start);
+    return decoded;
+}
 
-    // return the output without left over data
-    return str_buf.trim_end().to_string();
+// drains the pty on its own thread so output streams in while
+// commands are running, each read wakes the event loop
+pub fn spawn_pty_reader(mut reader: Box<dyn Read + Send>,
+                        proxy: EventLoopProxy<TermEvent>) -> thread::JoinHandle<()> {
+    return thread::spawn(move || {
+        let mut u8_buf = [0u8; 4096];
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            match reader.read(&mut u8_buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    pending.extend_from_slice(&u8_buf[..n]);
+                    let output = decode_utf8(&mut pending);
+                    if output.is_empty() {
+                        continue;
+                    }
+                    // loop has closed so nobody is listening
+                    if proxy.send_event(TermEvent::PtyOutput(output)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    println!("pty read error {}", _e);
+                    break;
+                }
+            }
+        }
+        let _ = proxy.send_event(TermEvent::PtyExit);
+    });
 }
 
+// corner of the unit quad every cell is drawn with
 #[repr(C)] #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
-    position: [f32; 3],
-    tex_coords: [f32; 2],
+    position: [f32; 2],
 }
 
 impl Vertex {
@@ -57,11 +128,6 @@ impl Vertex {
                 wgpu::VertexAttribute {
                     offset: 0,
                     shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
             ],
@@ -69,8 +135,71 @@ impl Vertex {
     }
 }
 
+// cell flags, must match the consts in shader.wgsl
+const HAS_GLPYH: u32 = 1;
+const UNDERLINE: u32 = 2;
+const STRIKE: u32 = 4;
+// the glpyh is in the colour page and drawn as is
+const COLOR: u32 = 8;
+// the cursor is on the cell, a block swaps the fg and bg before the shader
+// sees them, the other shapes are drawn in the fg
+const CURSOR_BLOCK: u32 = 16;
+const CURSOR_HOLLOW: u32 = 32;
+const CURSOR_UNDERLINE: u32 = 64;
+const CURSOR_BAR: u32 = 128;
+// nothing to draw but the default bg, which the clear already put there
+const BLANK: u32 = 256;
+
+// time the cursor is shown, then hidden, while blinking
+const BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+// per cell data, the vertex shader places the quad from the cell
+// and the fragment shader draws the bg, glpyh and lines
+#[repr(C)] #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct CellInstance {
+    // col, row
+    cell: [f32; 2],
+    // pixels from the top left of the cell to the glpyh
+    glpyh_offset: [f32; 2],
+    glpyh_size: [f32; 2],
+    // u, v, width, height in the atlas
+    uv: [f32; 4],
+    fg: [f32; 4],
+    bg: [f32; 4],
+    flags: u32,
+    // atlas page the uv is in
+    layer: u32,
+}
+
+impl CellInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        1 => Float32x2, 2 => Float32x2, 3 => Float32x2, 4 => Float32x4,
+        5 => Float32x4, 6 => Float32x4, 7 => Uint32, 8 => Uint32];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<CellInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// sizes in pixels the shader needs to lay out the grid
+#[repr(C)] #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    screen_size: [f32; 2],
+    cell_size: [f32; 2],
+    // y offset and thickness of the lines in a cell
+    underline: [f32; 2],
+    strike: [f32; 2],
+    // gamma, contrast, 1.0 for lcd coverage, unused
+    text: [f32; 4],
+}
+
 pub struct Pty {
-    pub reader: Box<dyn Read + Send>,
+    pub master: Box<dyn MasterPty + Send>,
     pub writer: Box<dyn Write + Send, Global>,
 }
  
@@ -79,29 +208,146 @@ pub struct State<'window>{
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     render_pipeline: wgpu::RenderPipeline,
-    // pub font_atlas: FontAtlas,
-    glpyhs: HashMap<char, wgpu::BindGroup>,
+    pub font_atlas: FontAtlas,
+    // the whole screen is drawn with this one bind group
+    atlas_bind: wgpu::BindGroup,
+    glpyh_sampler: wgpu::Sampler,
+    glpyh_layout: wgpu::BindGroupLayout,
     pub shell_buf : ShellBuf,
     term_config: TermConfig,
-    glpyh_indicies: [u16;6],
     glpyh_indicies_buf: wgpu::Buffer,
-    glpyh_loader : GlpyhLoader,
+    // the unit quad every cell is an instance of
+    quad_buf: wgpu::Buffer,
+    uniform_buf: wgpu::Buffer,
+    pub term: Term,
+    pub modifiers: ModifiersState,
+    // the cursor is a hollow block while the window isn't focused
+    pub focused: bool,
+    // blinking counts from here, typing restarts it so the cursor
+    // stays shown while keys are pressed
+    blink_start: Instant,
+    // (row, col, flags) of the cursor in the view as it was last drawn
+    drawn_cursor: Option<(usize, usize, u32)>,
     pub pty: Arc<Mutex<Pty>>,
     pub size: PhysicalSize<u32>,
     // must be delcared last
     surface: wgpu::Surface<'window>,
 }
 
+pub const USAGE: &str = "usage: hermitshell FONT [FALLBACK...] [--bold FONT] \
+    [--italic FONT] [--bold-italic FONT] [--line-height SCALE] \
+    [--letter-spacing PIXELS] [--scrollback LINES] [--antialias grayscale|lcd] \
+    [--gamma GAMMA] [--contrast CONTRAST]";
+
+// the value given after a flag
+fn flag_value<T: std::str::FromStr>(flag: &str, value: Option<String>)
+    -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    return value.parse().map_err(|_| format!("{} can't be {:?}", flag, value));
+}
+
+// reads the command line, without the program name
+// the first font is the primary, the rest are fallbacks
+// --bold, --italic and --bold-italic take the font for that face
+// --line-height scales the height of a line and
+// --letter-spacing adds pixels across each cell
+// --scrollback is how many lines of history are kept
+// --antialias is grayscale or lcd, --gamma and --contrast tune glpyh edges
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<TermConfig, String> {
+    let mut font_dirs: Vec<String> = Vec::new();
+    let (mut bold_font, mut italic_font, mut bold_italic_font) =
+        (None, None, None);
+    let (mut line_height, mut letter_spacing) = (1.0, 0.0);
+    let mut scrollback_lines = 10000;
+    let mut antialias = Antialias::Grayscale;
+    let (mut gamma, mut contrast): (f32, f32) = (1.0, 0.0);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--line-height" => line_height = flag_value(&arg, args.next())?,
+            "--letter-spacing" => letter_spacing = flag_value(&arg, args.next())?,
+            "--scrollback" => scrollback_lines = flag_value(&arg, args.next())?,
+            "--antialias" => antialias = match args.next().as_deref() {
+                Some("grayscale") => Antialias::Grayscale,
+                Some("lcd") => Antialias::Lcd,
+                _ => return Err("--antialias is grayscale or lcd".to_string()),
+            },
+            "--gamma" => gamma = flag_value(&arg, args.next())?,
+            "--contrast" => contrast = flag_value(&arg, args.next())?,
+            "--bold" => bold_font = args.next(),
+            "--italic" => italic_font = args.next(),
+            "--bold-italic" => bold_italic_font = args.next(),
+            _ => font_dirs.push(arg),
+        }
+    }
+    if !(gamma > 0.0 && gamma.is_finite()) {
+        return Err(format!("--gamma must be above 0, not {}", gamma));
+    }
+    if !(contrast >= 0.0 && contrast.is_finite()) {
+        return Err(format!("--contrast must be 0 or more, not {}", contrast));
+    }
+    if font_dirs.is_empty() {
+        return Err("no font given".to_string());
+    }
+
+    return Ok(TermConfig { font_dirs, bold_font, italic_font, bold_italic_font,
+        font_size: 32.0, scrollback_lines, antialias, gamma, contrast,
+        line_height, letter_spacing });
+}
+
 #[derive(Default)]
 pub struct App {
     window: Option<Arc<Window>>,
     state : Option<State<'static>>,
+    proxy: Option<EventLoopProxy<TermEvent>>,
+    // from the command line, the state is made from it once there is a window
+    term_config: Option<TermConfig>,
+    // why the state couldn't be made, the loop exits and main reports it
+    error: Option<String>,
+    // pty output since the last frame, drawn once the event queue is empty
+    redraw_pending: bool,
+}
+
+impl App {
+    pub fn new(proxy: EventLoopProxy<TermEvent>, term_config: TermConfig) -> Self {
+        return Self { proxy: Some(proxy), term_config: Some(term_config),
+                      ..Default::default() };
+    }
+
+    pub fn take_error(&mut self) -> Option<String> {
+        return self.error.take();
+    }
+
+    fn set_state(&mut self, event_loop: &ActiveEventLoop,
+                 state: Result<State<'static>, String>) {
+        match state {
+            Ok(state) => self.state = Some(state),
+            Err(e) => {
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
 }
 
+// an instance for every cell on screen, row by row, drawn with a single call
+// only damaged rows are rewritten and the buffer only grows with the grid
 pub struct ShellBuf {
-    pub scratch: String,
-    pub string_buf: String,
-    pub glpyhs_pos: Vec<wgpu::Buffer>
+    instances: Vec<CellInstance>,
+    instance_buf: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl ShellBuf {
+    fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let instance_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("cell instance buffer"),
+            size: (capacity * std::mem::size_of::<CellInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        return Self { instances: Vec::with_capacity(capacity), instance_buf,
+                      capacity };
+    }
 }
 
 use std::collections::HashMap;
@@ -124,15 +370,14 @@ pub fn remove_duplicates(mut s: String) -> (HashMap<char,i32>, String) {
 
 impl<'window> State<'window> {
     pub async fn async_new(window: Arc<Window>, term_config : TermConfig, 
-        pty: Arc<Mutex<Pty>>) -> State<'window> {
-        let (surface, mut device, mut queue, config) = 
+        pty: Arc<Mutex<Pty>>) -> Result<State<'window>, String> {
+        let (surface, mut device, queue, config) = 
             Self::surface_config(Arc::clone(&window)).await;
 
-        /*
-        // load fontatlas
-        let font_atlas = FontAtlas::new(term_config.clone(), &mut device,
-                                        &mut queue);
+        // glpyhs are added to the atlas as they are drawn
+        let font_atlas = FontAtlas::new(term_config.clone(), &device, &queue)?;
 
+        /*
         #[cfg(debug_assertions)]
         {
             println!("atlas texture complete creating dbg buffer...");
@@ -213,32 +458,50 @@ impl<'window> State<'window> {
         let (render_pipeline, glpyh_sampler, glpyh_layout) = 
             Self::make_render_pipeline(&mut device, config.format).await; 
 
-        let glpyh_loader = GlpyhLoader::new(term_config.clone());
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
-        let glpyhs = pollster::block_on(Self::make_glpyhs(&mut device, &mut queue, 
-                                       //&font_atlas, 
-                                       &glpyh_loader, 
-                                       glpyh_sampler, glpyh_layout));
+        let atlas_bind = Self::make_atlas_bind(&device, &font_atlas,
+                                               &glpyh_sampler, &glpyh_layout,
+                                               &uniform_buf);
      
-        // controls indicies for debug code and render
+        // controls indicies for the quad
         let glpyh_indicies: [u16;6] = [
              0, 3, 2,
              1, 3, 0
         ];
 
-
         // create buffer for position
         let glpyh_indicies_buf = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor { 
-                label: Some("dgb buffer indicies"),
+                label: Some("quad indicies"),
                 contents: bytemuck::cast_slice(&glpyh_indicies.clone()),
                 usage: wgpu::BufferUsages::INDEX,
         });
 
+        let quad: &[Vertex] = &[
+            Vertex { position: [0.0, 0.0] }, // t lh corner
+            Vertex { position: [0.0, 1.0] }, // b lh corner
+            Vertex { position: [1.0, 0.0] }, // t rh corner
+            Vertex { position: [1.0, 1.0] }, // b rh corner
+        ];
+        let quad_buf = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("quad vertices"),
+                contents: bytemuck::cast_slice(quad),
+                usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let shell_buf = ShellBuf::new(&device, 24 * 80);
+
         let size = window.inner_size();
 
         // pack into struct
-        return Self{
+        let mut state = Self{
                 pty,
                 surface,
                 device,
@@ -246,22 +509,29 @@ impl<'window> State<'window> {
                 config,
                 render_pipeline,
                 size,
-                // font_atlas,
-                shell_buf: ShellBuf{
-                    scratch: String::new(),
-                    string_buf: String::new(), 
-                    glpyhs_pos: vec![]
-                },
-                glpyhs,
+                font_atlas,
+                atlas_bind,
+                glpyh_sampler,
+                glpyh_layout,
+                shell_buf,
+                term: Term::new(24, 80, term_config.scrollback_lines),
                 term_config,
-                glpyh_indicies,
                 glpyh_indicies_buf,
-                glpyh_loader
-           }
+                quad_buf,
+                uniform_buf,
+                modifiers: ModifiersState::default(),
+                focused: true,
+                blink_start: Instant::now(),
+                drawn_cursor: None,
+           };
+
+        // size the grid and pty to the window
+        state.resize(size);
+        return Ok(state);
     }
 
     pub fn new(window: Arc<Window>, term_config : TermConfig, 
-        pty: Arc<Mutex<Pty>>) -> State<'window>{
+        pty: Arc<Mutex<Pty>>) -> Result<State<'window>, String> {
         return pollster::block_on(State::async_new(Arc::clone(&window), term_config, pty))
     }
             
@@ -273,7 +543,9 @@ impl<'window> State<'window> {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
+            // glpyhs are drawn 1:1 so nearest keeps the neighbours
+            // in the atlas from bleeding in
+            mag_filter: wgpu::FilterMode::Nearest,
             min_filter: wgpu::FilterMode::Nearest,
             mipmap_filter: wgpu::FilterMode::Nearest,
             ..Default::default()
@@ -287,7 +559,7 @@ impl<'window> State<'window> {
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
                             sample_type: wgpu::TextureSampleType::Float { 
                                 filterable: true
                             },
@@ -303,6 +575,29 @@ impl<'window> State<'window> {
                             wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX
+                            | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: true
+                            },
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("glpyh_bind_group_layout"),
             }); 
@@ -325,7 +620,7 @@ impl<'window> State<'window> {
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                     module: &shader,
                     entry_point: "vs_main",
-                    buffers: &[Vertex::desc()],
+                    buffers: &[Vertex::desc(), CellInstance::desc()],
                 },
             fragment: Some(wgpu::FragmentState {
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -333,10 +628,8 @@ impl<'window> State<'window> {
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format: config,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
+                    // cells are opaque, the shader blends the glpyh over the bg
+                    blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -424,207 +717,95 @@ impl<'window> State<'window> {
 
 
 
-       pub async fn make_glpyhs(device: &mut wgpu::Device, queue: &mut wgpu::Queue, // font_atlas: &FontAtlas, 
-                                glpyh_loader: &GlpyhLoader,
-                                glpyh_sampler: wgpu::Sampler, glpyh_layout: wgpu::BindGroupLayout) -> 
-        HashMap<char, wgpu::BindGroup> {
-
-        #[cfg(debug_assertions)]
-        println!("make glpyhs has been called");
-
-        let mut glpyhs: HashMap<char, wgpu::BindGroup> = HashMap::new();
-
-        /* HOTSWAP
-        // render each glpyh to texture
-        for glpyh in font_atlas.lookup.keys() {
-        */
-        for glpyh in glpyh_loader.glpyh_map.keys() {
-            // create buffer
-            let Some(glpyh_buf)
-                = glpyh_loader.get_glpyh_data(*glpyh, device, queue).await
-                    else { panic!("no glpyh found for {}", glpyh); };
-
-            let buffer_slice = glpyh_buf.slice(..);
-            
-            // NOTE: We have to create the mapping THEN device.poll() before await
-            // the future. Otherwise the application will freeze.
-            let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
-            buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                tx.send(result).unwrap();
+    pub fn make_atlas_bind(device: &wgpu::Device, font_atlas: &FontAtlas,
+                           glpyh_sampler: &wgpu::Sampler,
+                           glpyh_layout: &wgpu::BindGroupLayout,
+                           uniform_buf: &wgpu::Buffer) -> wgpu::BindGroup {
+        let view = font_atlas.atlas.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("font_atlas_view"),
+            format: Some(font_atlas.atlas.format()),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let color_view = font_atlas.color_atlas.create_view(
+            &wgpu::TextureViewDescriptor {
+                label: Some("font_atlas_color_view"),
+                ..Default::default()
             });
-            device.poll(wgpu::Maintain::Wait);
-            rx.receive().await.unwrap().unwrap();
-
-            let data = buffer_slice.get_mapped_range();
-
-            #[cfg(debug_assertions)]
-            println!("font_atlas lookup glpyh {}", glpyh);
-
-            {
-                #[cfg(debug_assertions)]
-                println!("polling device for glpyh {} self.started", glpyh);
-
-                device.poll(wgpu::Maintain::Wait);
-
-                #[cfg(debug_assertions)]
-                println!("found glpyh mapping to texture...");
-
-                    // create buf view and lookup bounding box
-                let Some((bbox, _)) = glpyh_loader.glpyh_map.get(glpyh) else 
-                    {panic!("no lookup for glpyh")};
-
-                let tex_size = wgpu::Extent3d{
-                            height: bbox.height as u32, 
-                            width: bbox.width as u32, 
-                            depth_or_array_layers: 1
-                        };
-
-                let glpyh_tex = device.create_texture(&wgpu::TextureDescriptor{
-                        label: Some(&format!("glpyh_tex {}", glpyh)),
-                        size: tex_size,
-                        mip_level_count: 1,
-                        sample_count: 1,
-                        dimension: wgpu::TextureDimension::D2,
-                        format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                            | wgpu::TextureUsages::TEXTURE_BINDING
-                            | wgpu::TextureUsages::COPY_DST
-                            | wgpu::TextureUsages::COPY_SRC,
-                        view_formats: &[wgpu::TextureFormat::Bgra8UnormSrgb],
-                });
-
-                #[cfg(debug_assertions)]
-                {
-                    println!("bbox size ({}, {})", bbox.width, bbox.height);
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("font_atlas_bindgroup"),
+            layout: glpyh_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(glpyh_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&color_view),
                 }
+            ],
+        });
+    }
 
-                let glpyh_encoder = device.create_command_encoder(
-                    &CommandEncoderDescriptor { label: Some(&format!("glpyh enc {}", glpyh))});
-
-                // write from buffer
-
-                /*
-                 * pulling out of the buffer directly doesn't work
-                 * glpyh_encoder.copy_buffer_to_texture(
-                    wgpu::ImageCopyBuffer { 
-                        buffer: &glpyh_buf, 
-                        layout: ImageDataLayout { 
-                            offset: 
-                            ((bbox.height * (bbox.width * 4).next_multiple_of(256))
-                                    - bbox.height * bbox.width).prev_multiple_of(&256),
-                            bytes_per_row: Some(((bbox.width * 4).next_multiple_of(256)) as u32),
-                            rows_per_image: Some(bbox.height as u32)}
-                    },
-                    ImageCopyTexture { 
-                        texture: &glpyh_tex, 
-                        mip_level: 0, 
-                        origin: wgpu::Origin3d::default(), 
-                        aspect: wgpu_types::TextureAspect::All, 
-                    }, tex_size);
-                */
-
-                // remove padding from buffer
-                let glpyh_slice = GlpyhLoader::depadder(data.to_vec(),
-                    bbox.width);
-
-                queue.write_texture(
-                    glpyh_tex.as_image_copy(), 
-                    glpyh_slice.as_slice(),
-                    ImageDataLayout { 
-                        offset: 0,
-                        bytes_per_row: Some((bbox.width * 4) as u32),
-                        rows_per_image: Some(bbox.height as u32)
-                    }, 
-                    tex_size);
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+            self.size = new_size;
 
-                #[cfg(debug_assertions)]
-                {
-                    use image::Rgba;
-                    match image::ImageBuffer::<Rgba<u8>,_>::from_raw(
-                       bbox.width as u32, bbox.height as u32, glpyh_slice.as_slice()){
-                        Some(im) => { 
-                           match im.save(format!("make_glpyh_{}.png", glpyh)) {
-                                Ok(()) => { println!("make_glpyh_{}.png saved!", glpyh); }
-                                Err(e) => {
-                                    println!("make_glpyh_{}.png not saved buf error - {}", glpyh, e);
-                                }
-                            }
-                        }
-                        None => {
-                            println!("buffer error in saving make_glpyh_{}.png", glpyh);
-                        }
+            // fit as many cells as we can in the window
+            let cell = self.cell_metrics();
+            let cols = ((new_size.width as f32 / cell.width) as usize).max(1);
+            let rows = ((new_size.height as f32 / cell.height) as usize).max(1);
+
+            if rows != self.term.grid.rows || cols != self.term.grid.cols {
+                self.term.resize(rows, cols);
+
+                // the child gets SIGWINCH from this
+                if let Ok(pty) = self.pty.lock().as_mut() {
+                    if let Err(_e) = pty.master.resize(PtySize {
+                        rows: rows as u16,
+                        cols: cols as u16,
+                        pixel_width: new_size.width as u16,
+                        pixel_height: new_size.height as u16
+                    }) {
+                        #[cfg(debug_assertions)]
+                        println!("pty resize error {}", _e);
                     }
                 }
-
-               // create view for bindgroup
-                let view = glpyh_tex.create_view(&wgpu::TextureViewDescriptor { 
-                    label: Some(&format!("tex view {}", glpyh)), 
-                    format: Some(wgpu::TextureFormat::Bgra8UnormSrgb), 
-                    dimension: Some(wgpu::TextureViewDimension::D2), 
-                    aspect: wgpu::TextureAspect::All, 
-                    base_mip_level: 0, 
-                    mip_level_count: None, 
-                    base_array_layer: 0, 
-                    array_layer_count: None 
-                });
-
-                // submit queue 
-                // should only do this once in future revisions
-                queue.submit(iter::once(glpyh_encoder.finish()));
-                device.poll(wgpu::Maintain::Wait);
-
-                let binder = device.create_bind_group(
-                    &wgpu::BindGroupDescriptor {
-                        label: Some(&format!("glpyh_bindgroup_{}", *glpyh)),
-                        layout: &glpyh_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::
-                                    TextureView(&view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::
-                                    Sampler(&glpyh_sampler),
-                            }
-                        ],
-                    });
-
-                // write texture to bindgroup using device.
-                glpyhs.insert(*glpyh, binder);
-
-                #[cfg(debug_assertions)]
-                println!("glpyh {} inserted to hashmap", *glpyh);
-
-                device.poll(wgpu::Maintain::Wait);
-                #[cfg(debug_assertions)]
-                println!("polling device for glpyh {} complete", *glpyh);
             }
+        }
 
-            #[cfg(debug_assertions)]
-            println!("self.starting submitted glpyh queue polling");
-
-            device.poll(wgpu::Maintain::Wait);
+        self.update()
+    }
 
+    // keeps the glpyhs rasterized this run for the next one
+    pub fn save_atlas(&mut self) {
+        if let Err(_e) = pollster::block_on(
+            self.font_atlas.save_cache(&self.device, &self.queue)) {
             #[cfg(debug_assertions)]
-            println!("finished glpyh queue polling");
-
-            // we do this in dtor not ctor 
-            // glpyh_buf.unmap
+            println!("atlas cache save error {}", _e);
         }
-        return glpyhs;
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.size = new_size;
+    pub fn write_to_pty(&mut self, bytes: &[u8]) {
+        if let Ok(pty) = self.pty.lock().as_mut() {
+            if let Err(_e) = pty.writer.write_all(bytes)
+                .and_then(|_| pty.writer.flush()) {
+                #[cfg(debug_assertions)]
+                println!("pty write error {}", _e);
+            }
         }
-
-        self.update()
     }
 
     #[allow(unused_variables)]
@@ -632,318 +813,335 @@ impl<'window> State<'window> {
         false
     }
 
-    pub fn update(&mut self) {
-        // set the position for drawing charecters
-        let mut layout = Layout::new(CoordinateSystem::PositiveYUp);
-        let fonts = &[self.glpyh_loader.font.clone()];
-        let start = (-1.0, 1.0);
-        layout.append(fonts, &TextStyle::new(&self.shell_buf.string_buf, 
-                self.term_config.font_size, 0));
-
-        let glpyhs = layout.glyphs();
-        for glpyh in glpyhs {
-            let (x, y) = 
-                ((glpyh.x / self.config.width as f32),
-                 (glpyh.y / self.config.height as f32));
-             
-            let (width, height) = 
-                ((glpyh.width as f32 / self.config.width as f32),
-                 (glpyh.height as f32 / self.config.height as f32));
-            /*
-             * this mirrors debug coords
-            let glpyh_vert: &[Vertex] = &[
-                Vertex { position: [0.0, 1.0, 0.0],
-                    tex_coords: [0.0, 0.0], }, // t lh corner
-                Vertex { position: [0.0, 0.0
-                    ,0.0], tex_coords: [0.0, 1.0]}, // b lh corner
-               Vertex { position: [1.0, 1.0 
-                    , 0.0], tex_coords: [1.0, 0.0], }, // t rh corner
-               Vertex { position: [1.0, 0.0, 0.0], 
-                    tex_coords: [1.0,1.0],},  // b rh corner
-              ];
-
-          */
-            // create coords:
-            // linestart pos , bbox width + pos, bbox height + height
-            let glpyh_vert: &[Vertex] = &[ 
-                Vertex { position: [start.0 + x, start.1 + y + height, 0.0],
-                        tex_coords: [0.0, 0.0]}, // t lh corner
-                Vertex { position: [start.0 + x, start.1 + y
-                    , 0.0], tex_coords: [0.0, 1.0], }, // b lh corner
-                Vertex { position: [start.0 + x + width, 
-                  start.1 + y + height, 0.0], tex_coords: [1.0,0.0]}, // t rh corner
-               Vertex { position: [start.0 + x + width,
-                start.1 + y,0.0], tex_coords: [1.0, 1.0], }, // b rh corner
-              ];
-
-            #[cfg(debug_assertions)]
-            {
-                println!("{:?}", glpyh_vert);
-            }
-
-           // create buffer for position
-            let glpyh_buf = self.device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor { 
-                    label: Some(&format!("buffer {}", glpyh.parent)),
-                    contents: bytemuck::cast_slice(glpyh_vert),
-                    usage: wgpu::BufferUsages::VERTEX,
-            });
+    // size of a grid cell in pixels and where glpyhs sit in it
+    pub fn cell_metrics(&self) -> CellMetrics {
+        return self.font_atlas.cell_metrics;
+    }
 
-            self.shell_buf.glpyhs_pos.push(glpyh_buf);
+    // view row, col and flags of the cursor as it is drawn at now. None
+    // while the app hides it, it is scrolled out of the view or it is in
+    // the off half of a blink
+    fn cursor(&self, now: Instant) -> Option<(usize, usize, u32)> {
+        let term = &self.term;
+        let row = term.grid.cursor.row + term.display_offset;
+        if !term.cursor_visible || row >= term.grid.rows {
+            return None;
+        }
+        let col = term.grid.cursor.col;
+        if !self.focused {
+            return Some((row, col, CURSOR_HOLLOW));
+        }
+        if term.cursor_style.blinking && self.blink_phase(now) % 2 == 1 {
+            return None;
+        }
+        match term.cursor_style.shape {
+            CursorShape::Block => return Some((row, col, CURSOR_BLOCK)),
+            CursorShape::Underline => return Some((row, col, CURSOR_UNDERLINE)),
+            CursorShape::Bar => return Some((row, col, CURSOR_BAR)),
         }
     }
 
-    // BIG OVERHEAD, creates copy of renderpipline to debug glpyh usage
-    pub async fn debug_glpyhs(&mut self) {
-       let instance = wgpu::Instance::new(wgpu::InstanceDescriptor { 
-           flags: Default::default(),
-           gles_minor_version: Default::default(),
-           backends: wgpu::Backends::all(),
-           dx12_shader_compiler: Default::default(),
-        });
-
-        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions{
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: None,
-            force_fallback_adapter: false,
-        }).await.unwrap();
-
-        // we use theese instead of self so we can copy over 
-        let (mut device, mut queue) = adapter.request_device(&Default::default(),
-            None).await.unwrap();
-
-        let (render_pipeline, glpyh_sampler, glpyh_layout) = 
-            Self::make_render_pipeline(&mut device, wgpu::TextureFormat::Rgba8UnormSrgb).await;
-        
-        // repopulate hashmap in font_atlas
-        // let font_atlas = FontAtlas::new(self.term_config.clone(), &mut device,
-        //                               &mut queue);
-        // we create new bindgoups and glpyhs so no overlap
-        let glpyh_loader_dgb = GlpyhLoader::new(self.term_config.clone());
-        let glpyhs = pollster::block_on(
-            Self::make_glpyhs(&mut device, &mut queue, // &mut font_atlas, 
-                                       &glpyh_loader_dgb, glpyh_sampler, glpyh_layout)
-            );
-        
+    // intervals since blinking started, the cursor is shown in even ones
+    fn blink_phase(&self, now: Instant) -> u128 {
+        return now.saturating_duration_since(self.blink_start).as_millis()
+            / BLINK_INTERVAL.as_millis();
+    }
 
-        let glpyh_indicies_buf = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor{
-                label: Some("dgb buffer indicies"),
-                contents: bytemuck::cast_slice(&self.glpyh_indicies.as_slice()),
-                usage: wgpu::BufferUsages::INDEX
-            });
+    // when the cursor next blinks, None if it is staying as it is
+    pub fn next_blink(&self, now: Instant) -> Option<Instant> {
+        if !self.focused || !self.term.cursor_visible
+            || !self.term.cursor_style.blinking {
+            return None;
+        }
+        let phase = self.blink_phase(now) as u32;
+        return Some(self.blink_start + BLINK_INTERVAL * (phase + 1));
+    }
 
-        // recreate loader to get texture sizes
-        for (glpyh, bindgroup) in glpyhs {
-            // create coords:
-            // self.start pos , bbox width + pos, bbox height + height
-            let Some((bbox, _)) = glpyh_loader_dgb.glpyh_map.get(&glpyh)
-            else { panic!("no bbox for glpyhs debugging") };
-
-            // bbox_normalized for multi char rendering
-            /* let bbox_normalized = 
-                        ((bbox.width as f64 / self.config.width as f64) as f32,
-                         (bbox.height as f64 / self.config.height as f64) as f32);
-                         */
-                   
-            let glpyh_vert: &[Vertex] = &[
-                Vertex { position: [0.0, 1.0, 0.0],
-                    tex_coords: [0.0, 0.0], }, // t lh corner
-                Vertex { position: [0.0, 0.0
-                    ,0.0], tex_coords: [0.0, 1.0]}, // b lh corner
-               Vertex { position: [1.0, 1.0 
-                    , 0.0], tex_coords: [1.0, 0.0], }, // t rh corner
-               Vertex { position: [1.0, 0.0, 0.0], 
-                    tex_coords: [1.0,1.0],},  // b rh corner
-          ];
-
-            // create buffer for position
-            let glpyh_positions = device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor { 
-                    label: Some("dgb buffer positions"),
-                    contents: bytemuck::cast_slice(glpyh_vert),
-                    usage: wgpu::BufferUsages::VERTEX,
-            });    
-
-            let label = format!("dgb desc glpyh {}", glpyh);
-            let tex_width = (bbox.width * 4).next_multiple_of(256).div_ceil(4);
-            let texture_desc = wgpu::TextureDescriptor {
-                size: wgpu::Extent3d {
-                    width: tex_width as u32,
-                    height: bbox.height as u32,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::COPY_SRC
-                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some(&label),
-                view_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb]
-            };
+    // shows the cursor from the start of a blink
+    pub fn restart_blink(&mut self) {
+        self.blink_start = Instant::now();
+    }
 
-            let texture = device.create_texture(&texture_desc);
-            let texture_view = texture.create_view(&Default::default());
+    // the cursor would be drawn differently from the last frame
+    pub fn cursor_changed(&self) -> bool {
+        return self.cursor(Instant::now()) != self.drawn_cursor;
+    }
 
-            // we need this for saving images later
-            let tex_size = ((4 * bbox.width as u32).next_multiple_of(256) * texture.height()) as u32;
+    // instance for a cell, glpyh is what to draw and how far right and up
+    // it is moved from the origin of the cell. cursor is the cursor flags
+    // if the cursor is on the cell
+    fn cell_instance(font_atlas: &mut FontAtlas, device: &wgpu::Device,
+                     queue: &wgpu::Queue, (row, col): (usize, usize), cell: &Cell,
+                     glpyh: Option<(Glpyh, [f32; 2])>, cursor: u32)
+        -> CellInstance {
+        let (mut fg, mut bg) = color::cell_colors(cell);
+        if cursor & CURSOR_BLOCK != 0 {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        let mut instance = CellInstance {
+            cell: [col as f32, row as f32],
+            fg: color::to_linear(fg),
+            bg: color::to_linear(bg),
+            flags: cursor,
+            ..Default::default()
+        };
 
-            let glpyh_dgb_buf_desc = wgpu::BufferDescriptor {
-                size: tex_size as u64,
-                usage: wgpu::BufferUsages::MAP_READ
-                    | wgpu::BufferUsages::COPY_DST,
-                label: Some(&label),
-                mapped_at_creation: false, 
-            };
-   
-            let mut encoder = device.create_command_encoder(
-                &wgpu::CommandEncoderDescriptor {
-                    label: Some("Render Debug Encoder"),
-            });
+        if cell.attrs & ATTR_UNDERLINE != 0 {
+            instance.flags |= UNDERLINE;
+        }
+        if cell.attrs & ATTR_STRIKE != 0 {
+            instance.flags |= STRIKE;
+        }
 
-            // encap for secuirty
-            {
-                let render_desc = wgpu::RenderPassDescriptor {
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                        label: Some("debug Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &texture_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    g: 1.0,
-                                    b: 0.0,
-                                    r: 0.0,
-                                    a: 1.0,
-                                }),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                    };
+        let Some((glpyh, shift)) = glpyh else { return instance };
+        // spaces have nothing to draw
+        if cell.attrs & ATTR_HIDDEN != 0 || glpyh == Glpyh::Char(' ') {
+            return instance;
+        }
+        if let Some((entry, uv)) = font_atlas.glpyh(glpyh, Self::face(cell),
+                                                    device, queue) {
+            // place the glpyh by its bearing from the origin of its cell
+            let (width, height) = (entry.size.0 as f32, entry.size.1 as f32);
+            let (x, y) = font_atlas.cell_metrics.origin;
+            instance.flags |= HAS_GLPYH;
+            if entry.color {
+                instance.flags |= COLOR;
+            }
+            instance.layer = entry.page;
+            instance.uv = uv;
+            instance.glpyh_offset = [x + entry.bearing.0 as f32 + shift[0],
+                y - entry.bearing.1 as f32 - shift[1] - height];
+            instance.glpyh_size = [width, height];
+        }
+        return instance;
+    }
 
-                let mut render_pass = encoder.begin_render_pass(&render_desc);
-                render_pass.set_pipeline(&render_pipeline);
+    fn face(cell: &Cell) -> Face {
+        return Face::new(cell.attrs & ATTR_BOLD != 0,
+                         cell.attrs & ATTR_ITALIC != 0);
+    }
 
-                // hack into render pipeline to render glpyh for dbg purposes
-                render_pass.set_bind_group(0, &bindgroup, &[]);
-                render_pass.set_vertex_buffer(0, glpyh_positions.slice(..));
-                render_pass.set_index_buffer(glpyh_indicies_buf.slice(..), wgpu::IndexFormat::Uint16);
-                println!("rendering glpyh {} for dgb", glpyh);
-                render_pass.draw_indexed(0..6, 0, 0..1);
-                println!("glpyh drawn"); 
+    // the glpyh each cell of a row draws, its own char and marks. the
+    // spacer after a wide char draws the right half of it
+    #[cfg(not(feature = "shaping"))]
+    fn row_glpyhs(font_atlas: &mut FontAtlas, cells: &[Cell])
+        -> Vec<Option<(Glpyh, [f32; 2])>> {
+        let cell_width = font_atlas.cell_metrics.width;
+        let mut glpyhs: Vec<Option<(Glpyh, [f32; 2])>> = Vec::with_capacity(cells.len());
+        for (col, cell) in cells.iter().enumerate() {
+            if cell.is_spacer() {
+                let wide = col > 0 && cells[col - 1].width == 2;
+                glpyhs.push(glpyhs.last().copied().flatten().filter(|_| wide)
+                    .map(|(glpyh, shift)| (glpyh, [shift[0] - cell_width, shift[1]])));
+            } else if cell.has_marks() {
+                glpyhs.push(Some((font_atlas.cluster(&cell.text()), [0.0, 0.0])));
+            } else {
+                glpyhs.push(Some((Glpyh::Char(cell.c), [0.0, 0.0])));
             }
- 
-            // don't present output
-            let glpyh_dbg_buf = device.create_buffer(&glpyh_dgb_buf_desc);
-
-            // save into buf from texture
-            encoder.copy_texture_to_buffer(
-                wgpu::ImageCopyTexture {
-                    aspect: wgpu::TextureAspect::All,
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO
-                },
-                wgpu::ImageCopyBuffer {
-                    buffer: &glpyh_dbg_buf,
-                    layout: wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: Some((4 * texture.width()).next_multiple_of(256)),
-                        rows_per_image: Some(texture.height()),
-                    },
-                }, texture.size());
-
-            // submit render + copy and no poll
-            queue.submit(Some(encoder.finish()));
+        }
+        return glpyhs;
+    }
 
-            #[cfg(debug_assertions)]
-            println!("glpyh copied to buf");
+    // the glpyh each cell of a row draws, from shaping runs of cells with
+    // the same attributes. a glpyh belongs to the cell its cluster starts
+    // in, where it sticks out of that cell it is also drawn shifted into
+    // neighbours in the run that have nothing of their own, like cells
+    // merged into a ligature or the spacer after a wide char. chars the
+    // font doesn't have go through the fallback chain unshaped, as do
+    // cells whose marks didn't shape into a single glpyh
+    #[cfg(feature = "shaping")]
+    fn row_glpyhs(font_atlas: &mut FontAtlas, cells: &[Cell])
+        -> Vec<Option<(Glpyh, [f32; 2])>> {
+        let cell_width = font_atlas.cell_metrics.width;
+        let mut glpyhs = vec![None; cells.len()];
+        let mut start = 0;
+        while start < cells.len() {
+            let first = cells[start];
+            let len = cells[start..].iter().take_while(|cell| {
+                return (cell.attrs, cell.fg, cell.bg) == (first.attrs, first.fg, first.bg);
+            }).count();
+            let run = &cells[start..start + len];
+            let placed = &mut glpyhs[start..start + len];
+            start += len;
+
+            // byte offset of each cell's text in the run, spacers add none
+            let mut text = String::new();
+            let mut offsets = Vec::with_capacity(run.len());
+            for cell in run {
+                offsets.push(text.len());
+                if !cell.is_spacer() {
+                    text.push(cell.c);
+                    text.extend(cell.marks());
+                }
+            }
 
-            // pull out the image from buffer
-            {
-                
-                let buffer_slice = glpyh_dbg_buf.slice(..);
-                
-                // NOTE: We have to create the mapping THEN device.poll() before await
-                // the future. Otherwise the application will freeze.
-                let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
-                buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                    tx.send(result).unwrap();
-                });
-                device.poll(wgpu::Maintain::Wait);
-                rx.receive().await.unwrap().unwrap();
-
-                let mut data = buffer_slice.get_mapped_range().to_vec();
-
-                use image::{Rgba, ImageBuffer};
-                let Some(buffer) =
-                   ImageBuffer::<Rgba<u8>, _>::from_raw(tex_width as u32, 
-                                                        texture.height() as u32,
-                        data.clone()) else { 
-                       println!("no glpyh printed to debug - QUIET FAIL");
-                       glpyh_dbg_buf.unmap();
-                       continue;
-                   };
-
-                let result = buffer.save(format!("glpyh_uncut_{}.png", glpyh.to_string()));
-                if let Err(e) = result {
-                    println!("uncut glpyh formatting error, glpyh: {}, error: {}",
-                                glpyh, e);
+            let face = Self::face(&first);
+            let shaped = font_atlas.shaper.shape(&text, face);
+            let mut count = vec![0; run.len()];
+            for glpyh in shaped.iter() {
+                let col = offsets.partition_point(|&offset| offset <= glpyh.cluster)
+                    .saturating_sub(1);
+                count[col] += 1;
+                if glpyh.id == 0 {
+                    placed[col].get_or_insert((Glpyh::Char(run[col].c), [0.0, 0.0]));
+                } else {
+                    placed[col].get_or_insert((Glpyh::Id(glpyh.id),
+                                               [glpyh.offset.0, glpyh.offset.1]));
                 }
-                else if let Ok(()) = result {
-                    println!("rendered and saved as glpyh_uncut_{}.png", glpyh); 
+            }
+            // only one glpyh fits in a cell
+            for (col, cell) in run.iter().enumerate() {
+                if cell.has_marks() && (count[col] > 1
+                    || matches!(placed[col], Some((Glpyh::Char(_), _)))) {
+                    placed[col] = Some((font_atlas.cluster(&cell.text()), [0.0, 0.0]));
                 }
-                else {
-                    println!("Unknown image formatting error");
+            }
+            // box drawing fills its own cell whatever the font made of it
+            for (col, cell) in run.iter().enumerate() {
+                if !cell.has_marks() && box_drawing::is_procedural(cell.c) {
+                    placed[col] = Some((Glpyh::Char(cell.c), [0.0, 0.0]));
                 }
+            }
 
-                // we want the end of our image so we reverse and modify 
-                {
-                    // reverse to get end of image
-                    data.reverse();
-                    // remove padding
-                    let rev_data = 
-                        GlpyhLoader::depadder(data, bbox.width);
-                    
-                    // resort the y axis
-                    let glpyh_slice: Vec<u8> = 
-                        rev_data.chunks((bbox.width * 4) as usize).rev()
-                        .fold(Vec::new(), |mut init, acc| {
-                            init.extend_from_slice(acc);
-                            return init;
-                        });
-           
-                
-                    // save the glpyh to .png
-                    let Some(buffer) =
-                       ImageBuffer::<Rgba<u8>, _>::from_raw(bbox.width as u32, 
-                                                            bbox.height as u32,
-                            glpyh_slice) else { 
-                           println!("no glpyh printed to debug - QUIET FAIL");
-                           glpyh_dbg_buf.unmap();
-                           continue;
-                       };
-
-                    let result = buffer.save(format!("glpyh_{}.png", glpyh.to_string()));
-                    if let Err(e) = result {
-                        println!("glpyh formatting error, glpyh: {}, error: {}",
-                                    glpyh, e);
+            // (left, right) pixels from the start of the run each cell's
+            // own glpyh covers, None where it draws nothing
+            let origin = font_atlas.cell_metrics.origin.0;
+            let ink: Vec<Option<(f32, f32)>> = placed.iter().enumerate()
+                .map(|(col, glpyh)| {
+                    let (glpyh, shift) = (*glpyh)?;
+                    let metrics = font_atlas.metrics(glpyh, face);
+                    if metrics.width == 0 || metrics.height == 0 {
+                        return None;
                     }
-                    else if let Ok(()) = result {
-                        println!("rendered and saved as glpyh_{}.png", glpyh); 
-                    }
-                    else {
-                        println!("Unknown image formatting error");
+                    let left = col as f32 * cell_width + origin + shift[0]
+                        + metrics.xmin as f32;
+                    return Some((left, left + metrics.width as f32));
+                }).collect();
+            for (col, span) in ink.iter().enumerate() {
+                let (Some((left, right)), Some((glpyh, shift))) = (*span, placed[col])
+                    else { continue };
+                let first_col = (left / cell_width).floor().max(0.0) as usize;
+                let last_col = ((right / cell_width).ceil() as usize).min(run.len());
+                for other in first_col..last_col {
+                    if other == col || ink[other].is_some() {
+                        continue;
                     }
+                    let back = (other as f32 - col as f32) * cell_width;
+                    placed[other] = Some((glpyh, [shift[0] - back, shift[1]]));
+                }
+            }
+        }
+        return glpyhs;
+    }
+
+    // rebuilds the damaged rows of the view, which can be scrolled back
+    // into history
+    fn build_rows(&mut self, instances: &mut [CellInstance], damage: &[bool]) {
+        let (rows, cols) = (self.term.grid.rows, self.term.grid.cols);
+        let clear = color::to_linear(color::DEFAULT_BG);
+        for row in (0..rows).filter(|&row| damage[row]) {
+            let cells = self.term.display_row(row);
+            // history lines can be narrower than the screen
+            let cells: Vec<Cell> = (0..cols)
+                .map(|col| cells.get(col).copied().unwrap_or_default()).collect();
+            let glpyhs = Self::row_glpyhs(&mut self.font_atlas, &cells);
+            for col in 0..cols {
+                let cursor = match self.drawn_cursor {
+                    Some((cursor_row, cursor_col, flags))
+                        if (cursor_row, cursor_col) == (row, col) => flags,
+                    _ => 0,
+                };
+                let mut instance = Self::cell_instance(
+                    &mut self.font_atlas, &self.device, &self.queue,
+                    (row, col), &cells[col], glpyhs[col], cursor);
+                if instance.flags == 0 && instance.bg == clear {
+                    instance.flags = BLANK;
+                }
+                instances[row * cols + col] = instance;
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        let cell = self.cell_metrics();
+        let font_size = self.term_config.font_size;
+        let line_width = (font_size / 14.0).ceil();
+        let baseline = cell.origin.1;
+
+        let lcd = self.font_atlas.antialias == Antialias::Lcd;
+        let uniforms = Uniforms {
+            screen_size: [self.config.width as f32, self.config.height as f32],
+            cell_size: [cell.width, cell.height],
+            // kept inside the cell when lines are squeezed together
+            underline: [(baseline + line_width).min(cell.height - line_width), line_width],
+            strike: [(baseline - cell.ascent / 3.0).round(), line_width],
+            text: [self.term_config.gamma.max(0.01),
+                   self.term_config.contrast.max(0.0),
+                   if lcd { 1.0 } else { 0.0 }, 0.0],
+        };
+        self.queue.write_buffer(&self.uniform_buf, 0,
+                                bytemuck::cast_slice(&[uniforms]));
+
+        let (rows, cols) = (self.term.grid.rows, self.term.grid.cols);
+        let mut damage = self.term.take_damage();
+
+        // a new grid size moves every cell
+        if self.shell_buf.instances.len() != rows * cols {
+            self.shell_buf.instances = vec![CellInstance::default(); rows * cols];
+            damage = vec![true; rows];
+            // the buffer only gets reallocated when the grid grows
+            if rows * cols > self.shell_buf.capacity {
+                let instances = std::mem::take(&mut self.shell_buf.instances);
+                self.shell_buf = ShellBuf::new(&self.device, rows * cols);
+                self.shell_buf.instances = instances;
+            }
+        }
+
+        // the rows the cursor left and moved to, it isn't part of the grid
+        // so the term doesn't damage them
+        let cursor = self.cursor(Instant::now());
+        if cursor != self.drawn_cursor {
+            for (row, _, _) in [cursor, self.drawn_cursor].into_iter().flatten() {
+                if row < rows {
+                    damage[row] = true;
                 }
             }
-            // unmap the output buffer
-            glpyh_dbg_buf.unmap();
+            self.drawn_cursor = cursor;
         }
-        return;
+
+        let mut instances = std::mem::take(&mut self.shell_buf.instances);
+        self.font_atlas.begin_frame();
+        let mut generation = self.font_atlas.generation;
+        self.build_rows(&mut instances, &damage);
+        // a new page or an evicted one leaves rows with stale uvs, pages are
+        // emptied at most once a frame so this settles after a few passes
+        while self.font_atlas.generation != generation {
+            generation = self.font_atlas.generation;
+            self.atlas_bind = Self::make_atlas_bind(&self.device, &self.font_atlas,
+                                                    &self.glpyh_sampler,
+                                                    &self.glpyh_layout,
+                                                    &self.uniform_buf);
+            damage = vec![true; rows];
+            self.build_rows(&mut instances, &damage);
+        }
+
+        // upload each run of damaged rows with one write
+        let row_bytes = (cols * std::mem::size_of::<CellInstance>()) as u64;
+        let mut row = 0;
+        while row < rows {
+            if !damage[row] {
+                row += 1;
+                continue;
+            }
+            let start = row;
+            while row < rows && damage[row] {
+                row += 1;
+            }
+            self.queue.write_buffer(&self.shell_buf.instance_buf,
+                start as u64 * row_bytes,
+                bytemuck::cast_slice(&instances[start * cols..row * cols]));
+        }
+        self.shell_buf.instances = instances;
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -951,6 +1149,7 @@ impl<'window> State<'window> {
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let clear = color::to_linear(color::DEFAULT_BG);
 
                     // we ignore DRY here because of debug operations
             let mut encoder = self
@@ -969,46 +1168,34 @@ impl<'window> State<'window> {
                                 view: &view,
                                 resolve_target: None,
                                 ops: wgpu::Operations {
+                                    // blank cells are dropped by the vertex
+                                    // shader and leave this showing
                                     load: wgpu::LoadOp::Clear(wgpu::Color {
-                                        g: 0.0,
-                                        r: 0.0,
-                                        b: 0.0,
-                                        a: 0.0,
+                                        r: clear[0] as f64,
+                                        g: clear[1] as f64,
+                                        b: clear[2] as f64,
+                                        a: 1.0,
                                     }),
-                                    store: wgpu::StoreOp::Discard,
+                                    store: wgpu::StoreOp::Store,
                                 },
                             })],
                             depth_stencil_attachment: None,
                         });
          
-                    if !self.shell_buf.string_buf.is_empty() {
+                    let instances = self.shell_buf.instances.len();
+                    if instances > 0 {
+                        let instance_bytes = (instances
+                            * std::mem::size_of::<CellInstance>()) as u64;
                         render_pass.set_pipeline(&self.render_pipeline);
-                        #[cfg(debug_assertions)]
-                        println!("shellbuf : {}", self.shell_buf.string_buf);
-
-                        for (i, chr) in self.shell_buf.string_buf.chars().enumerate() {
-                            
-                             #[cfg(debug_assertions)]
-                             // println!("chr {} printed to shell", chr);
-
-                            if chr != ' ' {
-                                if let Some(glpyh) = self.glpyhs.get(&chr) {
-                                    if let Some(positions) = 
-                                        self.shell_buf.glpyhs_pos.get(i) {
-                                        render_pass.set_bind_group(0, &glpyh, &[]);
-                                        render_pass.set_vertex_buffer(0, 
-                                                                      positions.slice(..));
-                                        render_pass.set_index_buffer(self.glpyh_indicies_buf.slice(..), wgpu::IndexFormat::Uint16);
-                                        render_pass.draw_indexed(0..6, 0, 0..1);
-                                        #[cfg(debug_assertions)]
-                                        println!("pixels rendered for glpyh {}", &chr);
-                                }
-                            }
-                        }
+                        render_pass.set_bind_group(0, &self.atlas_bind, &[]);
+                        render_pass.set_vertex_buffer(0, self.quad_buf.slice(..));
+                        render_pass.set_vertex_buffer(1,
+                            self.shell_buf.instance_buf.slice(..instance_bytes));
+                        render_pass.set_index_buffer(self.glpyh_indicies_buf.slice(..),
+                                                     wgpu::IndexFormat::Uint16);
+                        render_pass.draw_indexed(0..6, 0, 0..instances as u32);
                     }
-                
                 }
-            }
         self.queue.submit(iter::once(encoder.finish()));
         output.present();
 
@@ -1017,7 +1204,7 @@ impl<'window> State<'window> {
 }
 
 
-impl<'window> ApplicationHandler for App {
+impl<'window> ApplicationHandler<TermEvent> for App {
     fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
         if cause == StartCause::Init && self.window.is_none() {
             let window = event_loop.create_window(
@@ -1025,9 +1212,9 @@ impl<'window> ApplicationHandler for App {
                 self.window = Some(Arc::new(window));
 
                 let pty_system = native_pty_system();
-                // TODO: set pixel size to font size
+                // the real size is set once the font is loaded
                 let pty_pair = pty_system.openpty(
-                    PtySize { rows: 80, cols: 24, pixel_width: 18, pixel_height: 18})
+                    PtySize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0})
                         .unwrap();
 
                 // spawn os-specific shell
@@ -1039,21 +1226,26 @@ impl<'window> ApplicationHandler for App {
                 let cmd = CommandBuilder::new("bash");
                 pty_pair.slave.spawn_command(cmd).unwrap();
 
-                // create command reader for read_from_pty fn
+                // output is read on its own thread and sent back as events
                 let reader = pty_pair.master.try_clone_reader().unwrap();
+                if let Some(proxy) = &self.proxy {
+                    spawn_pty_reader(reader, proxy.clone());
+                }
 
-                use std::env;
-
-                let Some(font_dir) = env::args().nth(1) else {todo!()};
-             
                 // make buffers
                 // add carage return so that sh command self.starts up
                 let mut writer = pty_pair.master.take_writer().unwrap();
                 write!(writer, "\n").unwrap();
-                let pty = Arc::new(Mutex::new(Pty{reader,writer}));
-
-                self.state = Some(State::new(Arc::clone(self.window.as_ref().unwrap()),
-                    TermConfig { font_dir, font_size: 32.0}, pty)); 
+                let pty = Arc::new(Mutex::new(Pty{master: pty_pair.master, writer}));
+
+                let term_config = self.term_config.clone()
+                    .expect("the app is made with a config");
+                let state = State::new(Arc::clone(self.window.as_ref().unwrap()),
+                    term_config, pty);
+                self.set_state(event_loop, state);
+                if self.state.is_none() {
+                    return;
+                }
 
                 {
                     if let Some(win) = &self.window { 
@@ -1078,8 +1270,9 @@ impl<'window> ApplicationHandler for App {
                 self.window = Some(Arc::new(window));
                
             let pty = state.pty.borrow_mut();
-            self.state = Some(State::new(Arc::clone(&self.window.as_ref().unwrap()), 
-                    state.term_config.clone(), Arc::clone(pty)));
+            let state = State::new(Arc::clone(&self.window.as_ref().unwrap()), 
+                    state.term_config.clone(), Arc::clone(pty));
+            self.set_state(event_loop, state);
         }
         else{
             self.new_events(event_loop, StartCause::Init);
@@ -1092,70 +1285,74 @@ impl<'window> ApplicationHandler for App {
             if window.id() == win_id {
             if let Some(state)  = &mut self.state {
             match event {
-                WindowEvent::CloseRequested
-                | WindowEvent::KeyboardInput {
-                    event:
-                        KeyEvent {
-                            state: ElementState::Pressed,
-                            physical_key: PhysicalKey::Code(KeyCode::Escape),
-                            ..
-                        },
-                    ..
-                } => event_loop.exit(),
+                WindowEvent::CloseRequested => event_loop.exit(),
+                WindowEvent::Resized(new_size) => {
+                    state.resize(new_size);
+                    window.request_redraw();
+                }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    state.modifiers = modifiers.state();
+                }
+                WindowEvent::Focused(focused) => {
+                    state.focused = focused;
+                    state.restart_blink();
+                    window.request_redraw();
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => (y * 3.0) as isize,
+                        MouseScrollDelta::PixelDelta(pos) =>
+                            (pos.y as f32 / state.cell_metrics().height) as isize,
+                    };
+                    state.term.scroll_display(lines);
+                    window.request_redraw();
+                }
                 WindowEvent::KeyboardInput {
-                    event: KeyEvent { 
-                        state:ElementState::Pressed,
-                        physical_key: PhysicalKey::Code(KeyCode::Backspace), 
-                        ..},
-                    ..} => {
-                        // pop used to remove last char not for output
-                        state.shell_buf.scratch.pop().unwrap_or_default();
-                        state.shell_buf.string_buf.pop().unwrap_or_default();
-                        window.request_redraw();
-                    }
-                WindowEvent::KeyboardInput  {
                     event: KeyEvent {
-                        state:ElementState::Released,
-                        physical_key: PhysicalKey::Code(KeyCode::Enter),
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(page @ (NamedKey::PageUp
+                                                        | NamedKey::PageDown)),
                         ..},
-                    ..} => {
-                        // set window to and run command
-                        window.set_title(format!("hermitshell - {}", 
-                                state.shell_buf.scratch).as_str());
-                            
-                        if let Ok(pty) = state.pty.lock().as_mut() {
-                            writeln!(pty.writer, 
-                                "{}\r\n", state.shell_buf.scratch).unwrap();
-
-                            // clear buffer for next cmd
-                            state.shell_buf.scratch.clear();
-                            // push output to buffer
-                            let command_str = read_from_pty(&mut pty.reader);
-
-                            #[cfg(debug_assertions)]
-                            println!("{}", command_str);
-
-                            state.shell_buf.string_buf.push_str(&command_str);
+                    ..} if state.modifiers.shift_key() => {
+                    // shift+page scrolls the history instead of going to the app
+                    let page_size = state.term.grid.rows.saturating_sub(1).max(1)
+                        as isize;
+                    let lines = match page {
+                        NamedKey::PageUp => page_size,
+                        _ => -page_size,
+                    };
+                    state.term.scroll_display(lines);
+                    window.request_redraw();
+                }
+                WindowEvent::KeyboardInput { event, .. } => {
+                    // keys go straight to the pty, the shell does the echoing
+                    let flags = state.term.kitty_flags.current();
+                    let bytes = if flags != 0 {
+                        let event_type = match (event.state, event.repeat) {
+                            (ElementState::Released, _) => KeyEventType::Release,
+                            (ElementState::Pressed, true) => KeyEventType::Repeat,
+                            (ElementState::Pressed, false) => KeyEventType::Press,
+                        };
+                        kitty::encode(&event.logical_key,
+                            &event.key_without_modifiers(), event.location,
+                            event.text.as_deref(), state.modifiers, event_type,
+                            flags, state.term.key_modes)
+                    } else if event.state == ElementState::Pressed {
+                        keys::encode(&event.logical_key, event.location,
+                            event.text.as_deref(), state.modifiers,
+                            state.term.key_modes)
+                    } else {
+                        None
+                    };
+
+                    if let Some(bytes) = bytes {
+                        // typing snaps the view back to the live screen
+                        if state.term.display_offset > 0 {
+                            state.term.reset_display();
                             window.request_redraw();
                         }
-                   }       
-               WindowEvent::KeyboardInput {
-                   event: KeyEvent { logical_key: key, state: ElementState::Pressed, ..},
-                          ..} => {
-                    
-                    // unstable so split
-                    // if let Some(str_grab) = key.to_text() && str_grab.len() <= 1{
-                    if let Some(str_grab) = key.to_text() {
-                        if str_grab.len() <= 1 {
-                            if let Ok(_pty) = state.pty.lock().as_mut() {
-                                let char_grabbed = str_grab.chars().next().unwrap();     
-                                // char is borrowed here so we clone
-                                state.shell_buf.scratch.push(char_grabbed.clone());
-                                state.shell_buf.string_buf.push(char_grabbed.clone());
-                                // redraw code
-                                window.request_redraw();
-                            }
-                        }
+                        state.restart_blink();
+                        state.write_to_pty(&bytes);
                     }
                 }
                 WindowEvent::RedrawRequested =>{
@@ -1181,11 +1378,56 @@ impl<'window> ApplicationHandler for App {
         }
     }
 
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: TermEvent) {
+        match event {
+            TermEvent::PtyOutput(output) => {
+                if let Some(state) = &mut self.state {
+                    state.term.advance(&output);
+                    // answer any queries in the output
+                    if !state.term.responses.is_empty() {
+                        let responses = std::mem::take(&mut state.term.responses);
+                        state.write_to_pty(&responses);
+                    }
+                    if let Some(window) = &self.window {
+                        // title set by the shell through osc 0 or 2
+                        if let Some(title) = state.term.title.take() {
+                            window.set_title(&title);
+                        }
+                    }
+                    self.redraw_pending = true;
+                }
+            }
+            TermEvent::PtyExit => event_loop.exit(),
+        }
+    }
+
     fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, _event: DeviceEvent) {
         // Handle device event.
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            state.save_atlas();
+        }
+    }
+
+    // a burst of pty reads is handled before this is called so it
+    // only asks for one frame. the loop sleeps until the cursor next
+    // blinks, or until an event if it isn't blinking
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &self.state {
+            self.redraw_pending |= state.cursor_changed();
+            match state.next_blink(Instant::now()) {
+                Some(blink) => event_loop.set_control_flow(ControlFlow::WaitUntil(blink)),
+                None => event_loop.set_control_flow(ControlFlow::Wait),
+            }
+        }
+        if self.redraw_pending {
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+            self.redraw_pending = false;
+        }
     }
     
 }
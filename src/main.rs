@@ -1,15 +1,29 @@
-#![feature(int_roundings)]
-#![feature(slice_pattern)]
-
-use hermitshell::App;
-mod font_atlas;
-use hermitshell::font_atlas::font_atlas::TermConfig;
-
-use winit::event_loop::EventLoop;
-
-fn main(){
-    let event_loop = EventLoop::new().unwrap();
-    let app = &mut App::default();
-    event_loop.run_app(app).unwrap();
-}
-
+#![feature(int_roundings)]
+#![feature(slice_pattern)]
+
+use hermitshell::{parse_args, App, TermEvent, USAGE};
+mod font_atlas;
+use hermitshell::font_atlas::font_atlas::TermConfig;
+
+use winit::event_loop::EventLoop;
+
+// reports a bad command line or font and stops
+fn exit_with(error: &str) -> ! {
+    eprintln!("hermitshell: {}", error);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn main(){
+    let term_config: TermConfig = match parse_args(std::env::args().skip(1)) {
+        Ok(term_config) => term_config,
+        Err(e) => exit_with(&e),
+    };
+    let event_loop = EventLoop::<TermEvent>::with_user_event().build().unwrap();
+    let app = &mut App::new(event_loop.create_proxy(), term_config);
+    event_loop.run_app(app).unwrap();
+    if let Some(e) = app.take_error() {
+        exit_with(&e);
+    }
+}
+
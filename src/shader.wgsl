@@ -1,33 +1,154 @@
 // Vertex shader
 
+// every cell is drawn as an instance of one unit quad
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
+    @location(0) corner: vec2<f32>,
+}
+
+struct InstanceInput {
+    // col, row of the cell
+    @location(1) cell: vec2<f32>,
+    // pixels from the top left of the cell to the glpyh
+    @location(2) glpyh_offset: vec2<f32>,
+    @location(3) glpyh_size: vec2<f32>,
+    // u, v, width, height in the atlas
+    @location(4) uv: vec4<f32>,
+    @location(5) fg: vec4<f32>,
+    @location(6) bg: vec4<f32>,
+    @location(7) flags: u32,
+    // atlas page the uv is in
+    @location(8) layer: u32,
+}
+
+struct Uniforms {
+    screen_size: vec2<f32>,
+    cell_size: vec2<f32>,
+    // y offset and thickness of the lines in a cell, the cursor is
+    // drawn with lines as thick
+    underline: vec2<f32>,
+    strike: vec2<f32>,
+    // gamma, contrast, 1.0 when the atlas has lcd coverage
+    text: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
+    // pixel position inside the cell
+    @location(0) cell_pos: vec2<f32>,
+    @location(1) glpyh_offset: vec2<f32>,
+    @location(2) glpyh_size: vec2<f32>,
+    @location(3) uv: vec4<f32>,
+    @location(4) fg: vec4<f32>,
+    @location(5) bg: vec4<f32>,
+    @location(6) @interpolate(flat) flags: u32,
+    @location(7) @interpolate(flat) layer: u32,
 }
 
+const HAS_GLPYH: u32 = 1u;
+const UNDERLINE: u32 = 2u;
+const STRIKE: u32 = 4u;
+const COLOR: u32 = 8u;
+// a block cursor comes with its fg and bg swapped
+const CURSOR_BLOCK: u32 = 16u;
+const CURSOR_HOLLOW: u32 = 32u;
+const CURSOR_UNDERLINE: u32 = 64u;
+const CURSOR_BAR: u32 = 128u;
+const BLANK: u32 = 256u;
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(0)@binding(1)
+var s_diffuse: sampler;
+@group(0) @binding(2)
+var<uniform> uniforms: Uniforms;
+// colour glpyhs, straight alpha
+@group(0) @binding(3)
+var t_color: texture_2d<f32>;
+
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    // every corner in the same place makes a quad with no area, so
+    // cells only showing the clear colour cost no fragments
+    var corner = model.corner;
+    if (instance.flags & BLANK) != 0u {
+        corner = vec2<f32>(0.0);
+    }
+    let pixel = (instance.cell + corner) * uniforms.cell_size;
+    // pixels are y down from the top left
+    let clip = pixel / uniforms.screen_size * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(clip.x, -clip.y, 0.0, 1.0);
+    out.cell_pos = model.corner * uniforms.cell_size;
+    out.glpyh_offset = instance.glpyh_offset;
+    out.glpyh_size = instance.glpyh_size;
+    out.uv = instance.uv;
+    out.fg = instance.fg;
+    out.bg = instance.bg;
+    out.flags = instance.flags;
+    out.layer = instance.layer;
     return out;
 }
 
 // Fragment shader
 
-@group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
-@group(0)@binding(1)
-var s_diffuse: sampler;
+fn in_line(y: f32, line: vec2<f32>) -> bool {
+    return y >= line.x && y < line.x + line.y;
+}
+
+// the parts of the cell the cursor covers, other than a block
+fn in_cursor(pos: vec2<f32>, flags: u32) -> bool {
+    let line = uniforms.underline.y;
+    let size = uniforms.cell_size;
+    if (flags & CURSOR_HOLLOW) != 0u {
+        return any(pos < vec2<f32>(line)) || any(pos >= size - line);
+    }
+    if (flags & CURSOR_UNDERLINE) != 0u {
+        return pos.y >= size.y - line;
+    }
+    if (flags & CURSOR_BAR) != 0u {
+        return pos.x < line;
+    }
+    return false;
+}
 
+// gamma then contrast, both leave 0 and 1 where they are
+fn adjust(coverage: vec3<f32>) -> vec3<f32> {
+    let gamma = pow(max(coverage, vec3<f32>(1e-5)), vec3<f32>(1.0 / uniforms.text.x));
+    let contrast = uniforms.text.y;
+    return gamma * (contrast + 1.0) / (gamma * contrast + 1.0);
+}
+
+// the atlas holds glpyh coverage, one value for grayscale or one for each
+// channel for lcd. the fg is blended over the bg per channel, colours are
+// linear so the blend is too. colour glpyhs are blended over the bg by
+// their own alpha, the fg only adds its alpha
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    var bg = in.bg.rgb;
+    var coverage = vec3<f32>(0.0);
+    let glpyh_pos = (in.cell_pos - in.glpyh_offset) / in.glpyh_size;
+    let uv = in.uv.xy + glpyh_pos * in.uv.zw;
+    if (in.flags & HAS_GLPYH) != 0u
+        && all(glpyh_pos >= vec2<f32>(0.0)) && all(glpyh_pos < vec2<f32>(1.0)) {
+        let texel = textureSampleLevel(t_diffuse, s_diffuse, uv, in.layer, 0.0);
+        if (in.flags & COLOR) != 0u {
+            let color = textureSampleLevel(t_color, s_diffuse, uv, 0.0);
+            bg = mix(bg, color.rgb, color.a * in.fg.a);
+        } else if uniforms.text.z > 0.5 {
+            coverage = adjust(texel.rgb);
+        } else {
+            coverage = adjust(vec3<f32>(texel.r));
+        }
+    }
+
+    if ((in.flags & UNDERLINE) != 0u && in_line(in.cell_pos.y, uniforms.underline))
+        || ((in.flags & STRIKE) != 0u && in_line(in.cell_pos.y, uniforms.strike))
+        || in_cursor(in.cell_pos, in.flags) {
+        coverage = vec3<f32>(1.0);
+    }
+
+    return vec4<f32>(mix(bg, in.fg.rgb, coverage * in.fg.a), 1.0);
 }
@@ -0,0 +1,90 @@
+/*
+ *  colour palette, turns the colours stored in cells into rgb
+ *  the 256 colours follow xterm: 16 ansi colours, a 6x6x6 cube
+ *  and a 24 step grey ramp
+ */
+use crate::terminal::grid::{Cell, Color, ATTR_BOLD, ATTR_DIM, ATTR_HIDDEN,
+                            ATTR_REVERSE};
+
+pub const DEFAULT_FG: [u8; 3] = [0xe5, 0xe5, 0xe5];
+pub const DEFAULT_BG: [u8; 3] = [0x00, 0x00, 0x00];
+
+// xterm's default ansi colours, 8-15 are the bright versions
+const ANSI: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xcd, 0x00, 0x00],
+    [0x00, 0xcd, 0x00],
+    [0xcd, 0xcd, 0x00],
+    [0x00, 0x00, 0xee],
+    [0xcd, 0x00, 0xcd],
+    [0x00, 0xcd, 0xcd],
+    [0xe5, 0xe5, 0xe5],
+    [0x7f, 0x7f, 0x7f],
+    [0xff, 0x00, 0x00],
+    [0x00, 0xff, 0x00],
+    [0xff, 0xff, 0x00],
+    [0x5c, 0x5c, 0xff],
+    [0xff, 0x00, 0xff],
+    [0x00, 0xff, 0xff],
+    [0xff, 0xff, 0xff],
+];
+
+pub fn indexed(idx: u8) -> [u8; 3] {
+    match idx {
+        0..=15 => return ANSI[idx as usize],
+        // 6x6x6 cube
+        16..=231 => {
+            let idx = idx - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            return [level(idx / 36), level((idx / 6) % 6), level(idx % 6)];
+        }
+        // grey ramp
+        _ => {
+            let grey = 8 + (idx - 232) * 10;
+            return [grey, grey, grey];
+        }
+    }
+}
+
+pub fn to_rgb(color: Color, default: [u8; 3]) -> [u8; 3] {
+    match color {
+        Color::Default => return default,
+        Color::Indexed(idx) => return indexed(idx),
+        Color::Rgb(r, g, b) => return [r, g, b],
+    }
+}
+
+// fg and bg of a cell once bold, dim, reverse and hidden are applied
+pub fn cell_colors(cell: &Cell) -> ([u8; 3], [u8; 3]) {
+    // bold brightens the first 8 colours like most terminals
+    let fg = match cell.fg {
+        Color::Indexed(idx) if idx < 8 && cell.attrs & ATTR_BOLD != 0 =>
+            Color::Indexed(idx + 8),
+        fg => fg,
+    };
+    let mut fg = to_rgb(fg, DEFAULT_FG);
+    let mut bg = to_rgb(cell.bg, DEFAULT_BG);
+
+    if cell.attrs & ATTR_DIM != 0 {
+        fg = fg.map(|v| (v as u16 * 2 / 3) as u8);
+    }
+    if cell.attrs & ATTR_REVERSE != 0 {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    if cell.attrs & ATTR_HIDDEN != 0 {
+        fg = bg;
+    }
+    return (fg, bg);
+}
+
+// the surface is srgb so colours go to the shader as linear
+pub fn to_linear(rgb: [u8; 3]) -> [f32; 4] {
+    let linear = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 {
+            return v / 12.92;
+        }
+        return ((v + 0.055) / 1.055).powf(2.4);
+    };
+    return [linear(rgb[0]), linear(rgb[1]), linear(rgb[2]), 1.0];
+}
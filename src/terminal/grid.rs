@@ -0,0 +1,264 @@
+/*
+ *  screen model for the terminal
+ *  rows x cols of cells with a cursor, the renderer draws
+ *  each cell at its grid position instead of laying out a string
+ */
+
+// cell attributes, stored as bits in Cell::attrs
+pub const ATTR_BOLD: u16 = 1 << 0;
+pub const ATTR_DIM: u16 = 1 << 1;
+pub const ATTR_ITALIC: u16 = 1 << 2;
+pub const ATTR_UNDERLINE: u16 = 1 << 3;
+pub const ATTR_BLINK: u16 = 1 << 4;
+pub const ATTR_REVERSE: u16 = 1 << 5;
+pub const ATTR_HIDDEN: u16 = 1 << 6;
+pub const ATTR_STRIKE: u16 = 1 << 7;
+
+// combining marks kept on a cell, any more are dropped
+pub const MAX_MARKS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: u16,
+    // number of columns the char takes up, a wide char is 2 and is
+    // followed by a spacer cell of width 0
+    pub width: u8,
+    // combining marks drawn over c, unused slots are '\0'
+    pub marks: [char; MAX_MARKS],
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        return Self {
+            c: ' ',
+            fg: Color::Default,
+            bg: Color::Default,
+            attrs: 0,
+            width: 1,
+            marks: ['\0'; MAX_MARKS],
+        };
+    }
+}
+
+impl Cell {
+    // blank cell keeping the background of the template
+    // used for erasing as per xterm's back colour erase
+    pub fn blank(template: &Cell) -> Self {
+        return Self { bg: template.bg, ..Default::default() };
+    }
+
+    // second column of a wide char
+    pub fn spacer(template: &Cell) -> Self {
+        return Self { c: ' ', width: 0, marks: ['\0'; MAX_MARKS], ..*template };
+    }
+
+    pub fn is_spacer(&self) -> bool {
+        return self.width == 0;
+    }
+
+    pub fn has_marks(&self) -> bool {
+        return self.marks[0] != '\0';
+    }
+
+    // adds a combining mark, false if there is no room for it
+    pub fn push_mark(&mut self, mark: char) -> bool {
+        let Some(slot) = self.marks.iter_mut().find(|slot| **slot == '\0')
+            else { return false };
+        *slot = mark;
+        return true;
+    }
+
+    pub fn marks(&self) -> impl Iterator<Item = char> + '_ {
+        return self.marks.iter().copied().take_while(|&mark| mark != '\0');
+    }
+
+    // the char and its marks, the grapheme cluster drawn in the cell
+    pub fn text(&self) -> String {
+        let mut text = String::from(self.c);
+        text.extend(self.marks());
+        return text;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cursor {
+    pub row: usize,
+    pub col: usize,
+}
+
+pub struct Grid {
+    pub rows: usize,
+    pub cols: usize,
+    pub cursor: Cursor,
+    cells: Vec<Vec<Cell>>,
+    // rows changed since the renderer last took the damage
+    dirty: Vec<bool>,
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        return Self {
+            rows,
+            cols,
+            cursor: Cursor::default(),
+            cells: vec![vec![Cell::default(); cols]; rows],
+            dirty: vec![true; rows],
+        };
+    }
+
+    pub fn mark_dirty(&mut self, row: usize) {
+        self.dirty[row] = true;
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.iter_mut().for_each(|dirty| *dirty = true);
+    }
+
+    // hands back the dirty rows and marks everything clean
+    pub fn take_dirty(&mut self) -> Vec<bool> {
+        return std::mem::replace(&mut self.dirty, vec![false; self.rows]);
+    }
+
+    pub fn row(&self, row: usize) -> &[Cell] {
+        return &self.cells[row];
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> &Cell {
+        return &self.cells[row][col];
+    }
+
+    pub fn cell_mut(&mut self, row: usize, col: usize) -> &mut Cell {
+        self.dirty[row] = true;
+        return &mut self.cells[row][col];
+    }
+
+    // blanks the other half of a wide char at col before it is written
+    // over, so no half of one is left behind
+    pub fn split_wide(&mut self, row: usize, col: usize, template: &Cell) {
+        let cell = self.cells[row][col];
+        if cell.is_spacer() && col > 0 {
+            self.cells[row][col - 1] = Cell::blank(template);
+        } else if cell.width == 2 && col + 1 < self.cols {
+            self.cells[row][col + 1] = Cell::blank(template);
+        }
+        self.dirty[row] = true;
+    }
+
+    // blanks both halves of a wide char cut in two by a boundary just
+    // before col, so shifting or truncating the row doesn't orphan either
+    fn split_at(&mut self, row: usize, col: usize, template: &Cell) {
+        if col < self.cols && self.cells[row][col].is_spacer() {
+            self.split_wide(row, col, template);
+            self.cells[row][col] = Cell::blank(template);
+        }
+    }
+
+    // fills cols [start, end) of a row with blanks
+    pub fn clear_cols(&mut self, row: usize, start: usize, end: usize,
+                      template: &Cell) {
+        let end = end.min(self.cols);
+        self.dirty[row] = true;
+        for cell in &mut self.cells[row][start.min(end)..end] {
+            *cell = Cell::blank(template);
+        }
+    }
+
+    pub fn clear_rows(&mut self, start: usize, end: usize, template: &Cell) {
+        for row in start..end.min(self.rows) {
+            self.clear_cols(row, 0, self.cols, template);
+        }
+    }
+
+    // truncates or pads the grid to the new size
+    // rows are dropped from the top so the cursor stays on screen,
+    // these are handed back for the scrollback
+    pub fn resize(&mut self, rows: usize, cols: usize) -> Vec<Vec<Cell>> {
+        let mut dropped = Vec::new();
+        if rows < self.rows {
+            let overflow = (self.cursor.row + 1).saturating_sub(rows);
+            dropped = self.cells.drain(..overflow).collect();
+            self.cursor.row -= overflow;
+        }
+        self.cells.truncate(rows);
+        for row in 0..self.cells.len() {
+            self.split_at(row, cols, &Cell::default());
+            self.cells[row].resize(cols, Cell::default());
+        }
+        self.cells.resize(rows, vec![Cell::default(); cols]);
+
+        self.rows = rows;
+        self.cols = cols;
+        self.dirty = vec![true; rows];
+        self.cursor.row = self.cursor.row.min(rows - 1);
+        self.cursor.col = self.cursor.col.min(cols - 1);
+        return dropped;
+    }
+
+    // moves rows in [top, bottom] up by n, blanking the bottom
+    // returns the rows that were scrolled out of the region
+    pub fn scroll_up(&mut self, top: usize, bottom: usize, n: usize,
+                     template: &Cell) -> Vec<Vec<Cell>> {
+        let n = n.min(bottom + 1 - top);
+        self.dirty[top..=bottom].iter_mut().for_each(|dirty| *dirty = true);
+        let region = &mut self.cells[top..=bottom];
+        region.rotate_left(n);
+        let len = region.len();
+        let mut scrolled = Vec::with_capacity(n);
+        for row in &mut region[(len - n)..] {
+            scrolled.push(std::mem::replace(row,
+                vec![Cell::blank(template); self.cols]));
+        }
+        return scrolled;
+    }
+
+    // moves rows in [top, bottom] down by n, blanking the top
+    pub fn scroll_down(&mut self, top: usize, bottom: usize, n: usize,
+                       template: &Cell) {
+        let n = n.min(bottom + 1 - top);
+        self.dirty[top..=bottom].iter_mut().for_each(|dirty| *dirty = true);
+        let region = &mut self.cells[top..=bottom];
+        region.rotate_right(n);
+        for row in &mut region[..n] {
+            *row = vec![Cell::blank(template); self.cols];
+        }
+    }
+
+    // ICH, shifts the rest of the line right
+    pub fn insert_blanks(&mut self, row: usize, col: usize, n: usize,
+                         template: &Cell) {
+        self.dirty[row] = true;
+        let n = n.min(self.cols - col);
+        self.split_at(row, col, template);
+        self.split_at(row, self.cols - n, template);
+        let line = &mut self.cells[row][col..];
+        line.rotate_right(n);
+        for cell in &mut line[..n] {
+            *cell = Cell::blank(template);
+        }
+    }
+
+    // DCH, shifts the rest of the line left
+    pub fn delete_cells(&mut self, row: usize, col: usize, n: usize,
+                        template: &Cell) {
+        self.dirty[row] = true;
+        let n = n.min(self.cols - col);
+        self.split_at(row, col, template);
+        self.split_at(row, col + n, template);
+        let line = &mut self.cells[row][col..];
+        line.rotate_left(n);
+        let len = line.len();
+        for cell in &mut line[(len - n)..] {
+            *cell = Cell::blank(template);
+        }
+    }
+}
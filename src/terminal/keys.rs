@@ -0,0 +1,182 @@
+/*
+ *  encodes winit key events into the bytes a terminal sends to the pty
+ *  this follows xterm's defaults, see "PC-Style Function Keys" in ctlseqs
+ *
+ *  modifiers are sent as a param of 1 + (shift 1 | alt 2 | ctrl 4 | super 8)
+ *  so ctrl+up is CSI 1;5A
+ */
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+
+// modes set by the application that change what keys send
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KeyModes {
+    // DECCKM, cursor keys send SS3 instead of CSI
+    pub app_cursor: bool,
+    // DECKPAM, the keypad sends SS3 sequences instead of digits
+    pub app_keypad: bool,
+}
+
+// xterm modifier param, 1 means no modifiers
+pub fn modifier_param(mods: ModifiersState) -> u8 {
+    let mut param = 0;
+    if mods.shift_key() { param |= 1; }
+    if mods.alt_key() { param |= 2; }
+    if mods.control_key() { param |= 4; }
+    if mods.super_key() { param |= 8; }
+    return param + 1;
+}
+
+// keys that send CSI final or SS3 final
+// the final char is used for both forms
+fn ss3_key(key: NamedKey) -> Option<char> {
+    match key {
+        NamedKey::ArrowUp => return Some('A'),
+        NamedKey::ArrowDown => return Some('B'),
+        NamedKey::ArrowRight => return Some('C'),
+        NamedKey::ArrowLeft => return Some('D'),
+        NamedKey::Home => return Some('H'),
+        NamedKey::End => return Some('F'),
+        NamedKey::F1 => return Some('P'),
+        NamedKey::F2 => return Some('Q'),
+        NamedKey::F3 => return Some('R'),
+        NamedKey::F4 => return Some('S'),
+        _ => return None,
+    }
+}
+
+// keys that send CSI number ~
+fn tilde_key(key: NamedKey) -> Option<u8> {
+    match key {
+        NamedKey::Insert => return Some(2),
+        NamedKey::Delete => return Some(3),
+        NamedKey::PageUp => return Some(5),
+        NamedKey::PageDown => return Some(6),
+        NamedKey::F5 => return Some(15),
+        NamedKey::F6 => return Some(17),
+        NamedKey::F7 => return Some(18),
+        NamedKey::F8 => return Some(19),
+        NamedKey::F9 => return Some(20),
+        NamedKey::F10 => return Some(21),
+        NamedKey::F11 => return Some(23),
+        NamedKey::F12 => return Some(24),
+        _ => return None,
+    }
+}
+
+// keypad chars in application keypad mode
+fn keypad_final(chr: char) -> Option<char> {
+    match chr {
+        '0'..='9' => return Some((b'p' + (chr as u8 - b'0')) as char),
+        '.' => return Some('n'),
+        '+' => return Some('k'),
+        '-' => return Some('m'),
+        '*' => return Some('j'),
+        '/' => return Some('o'),
+        '=' => return Some('X'),
+        _ => return None,
+    }
+}
+
+// the C0 control sent for ctrl+chr
+pub fn ctrl_char(chr: char) -> Option<u8> {
+    match chr {
+        '@' | ' ' | '2' => return Some(0x00),
+        'a'..='z' => return Some(chr as u8 - b'a' + 1),
+        '['..='_' => return Some(chr as u8 & 0x1f),
+        '3' => return Some(0x1b),
+        '4' => return Some(0x1c),
+        '5' => return Some(0x1d),
+        '6' => return Some(0x1e),
+        '7' | '/' => return Some(0x1f),
+        '8' | '?' => return Some(0x7f),
+        _ => return None,
+    }
+}
+
+fn alt_prefix(alt: bool, mut bytes: Vec<u8>) -> Vec<u8> {
+    if alt {
+        bytes.insert(0, 0x1b);
+    }
+    return bytes;
+}
+
+pub fn encode(key: &Key, location: KeyLocation, text: Option<&str>,
+              mods: ModifiersState, modes: KeyModes) -> Option<Vec<u8>> {
+    let param = modifier_param(mods);
+    let alt = mods.alt_key();
+
+    match key {
+        Key::Named(named) => {
+            if let Some(chr) = ss3_key(*named) {
+                if param > 1 {
+                    return Some(format!("\x1b[1;{}{}", param, chr).into_bytes());
+                }
+                let function_key = matches!(named, NamedKey::F1 | NamedKey::F2
+                                            | NamedKey::F3 | NamedKey::F4);
+                if function_key || modes.app_cursor {
+                    return Some(format!("\x1bO{}", chr).into_bytes());
+                }
+                return Some(format!("\x1b[{}", chr).into_bytes());
+            }
+
+            if let Some(number) = tilde_key(*named) {
+                if param > 1 {
+                    return Some(format!("\x1b[{};{}~", number, param).into_bytes());
+                }
+                return Some(format!("\x1b[{}~", number).into_bytes());
+            }
+
+            match named {
+                NamedKey::Enter => {
+                    if modes.app_keypad && location == KeyLocation::Numpad {
+                        return Some(b"\x1bOM".to_vec());
+                    }
+                    return Some(alt_prefix(alt, b"\r".to_vec()));
+                }
+                NamedKey::Backspace => {
+                    let erase = if mods.control_key() { 0x08 } else { 0x7f };
+                    return Some(alt_prefix(alt, vec![erase]));
+                }
+                NamedKey::Tab => {
+                    if mods.shift_key() {
+                        return Some(b"\x1b[Z".to_vec());
+                    }
+                    return Some(alt_prefix(alt, b"\t".to_vec()));
+                }
+                NamedKey::Escape => return Some(alt_prefix(alt, b"\x1b".to_vec())),
+                NamedKey::Space => {
+                    if mods.control_key() {
+                        return Some(alt_prefix(alt, vec![0x00]));
+                    }
+                    return Some(alt_prefix(alt, b" ".to_vec()));
+                }
+                _ => return None,
+            }
+        }
+        Key::Character(chars) => {
+            let mut iter = chars.chars();
+            let chr = iter.next()?;
+            let single = iter.next().is_none();
+
+            if single && location == KeyLocation::Numpad && modes.app_keypad {
+                if let Some(fin) = keypad_final(chr) {
+                    return Some(format!("\x1bO{}", fin).into_bytes());
+                }
+            }
+
+            if single && mods.control_key() {
+                if let Some(ctrl) = ctrl_char(chr.to_ascii_lowercase()) {
+                    return Some(alt_prefix(alt, vec![ctrl]));
+                }
+            }
+
+            // prefer the text winit gives as it has the layout applied
+            let text = text.unwrap_or(chars.as_str());
+            if text.is_empty() {
+                return None;
+            }
+            return Some(alt_prefix(alt, text.as_bytes().to_vec()));
+        }
+        _ => return text.map(|text| alt_prefix(alt, text.as_bytes().to_vec())),
+    }
+}
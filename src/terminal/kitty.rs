@@ -0,0 +1,285 @@
+/*
+ *  kitty's progressive enhancement keyboard protocol
+ *  https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+ *
+ *  apps push flags with CSI > flags u and keys are then reported as
+ *  CSI key-code:shifted-key:base-key ; modifiers:event-type ; text u
+ *  when no flags are set the legacy encoding in keys.rs is used
+ */
+use crate::terminal::keys::{self, modifier_param, KeyModes};
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+
+pub const DISAMBIGUATE: u8 = 0b1;
+pub const REPORT_EVENTS: u8 = 0b10;
+pub const REPORT_ALTERNATES: u8 = 0b100;
+pub const REPORT_ALL_KEYS: u8 = 0b1000;
+pub const REPORT_TEXT: u8 = 0b10000;
+pub const ALL_FLAGS: u8 = 0b11111;
+
+// pushes past this drop the oldest entry
+const MAX_STACK: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyEventType {
+    Press = 1,
+    Repeat = 2,
+    Release = 3,
+}
+
+// the stack of flags pushed by applications
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KittyFlags {
+    stack: Vec<u8>,
+}
+
+impl KittyFlags {
+    pub fn current(&self) -> u8 {
+        return self.stack.last().copied().unwrap_or(0);
+    }
+
+    // CSI > flags u
+    pub fn push(&mut self, flags: u8) {
+        if self.stack.len() >= MAX_STACK {
+            self.stack.remove(0);
+        }
+        self.stack.push(flags & ALL_FLAGS);
+    }
+
+    // CSI < n u, popping too many empties the stack
+    pub fn pop(&mut self, n: usize) {
+        let len = self.stack.len();
+        self.stack.truncate(len.saturating_sub(n));
+    }
+
+    // CSI = flags ; mode u
+    // mode 1 replaces, 2 sets the given bits, 3 clears them
+    pub fn set(&mut self, flags: u8, mode: u16) {
+        let current = self.current();
+        let flags = match mode {
+            2 => current | flags,
+            3 => current & !flags,
+            _ => flags,
+        } & ALL_FLAGS;
+
+        match self.stack.last_mut() {
+            Some(top) => *top = flags,
+            None => self.stack.push(flags),
+        }
+    }
+}
+
+// how a key is written in the protocol
+enum KittyKey {
+    // CSI code u
+    Code(u32),
+    // CSI 1 ; mods final, the legacy letter form
+    Letter(char),
+    // CSI number ; mods ~
+    Tilde(u32),
+}
+
+fn named_key(key: NamedKey, location: KeyLocation) -> Option<KittyKey> {
+    let numpad = location == KeyLocation::Numpad;
+    let left = location != KeyLocation::Right;
+    let kitty_key = match key {
+        NamedKey::Escape => KittyKey::Code(27),
+        NamedKey::Enter if numpad => KittyKey::Code(57414),
+        NamedKey::Enter => KittyKey::Code(13),
+        NamedKey::Tab => KittyKey::Code(9),
+        NamedKey::Backspace => KittyKey::Code(127),
+        NamedKey::Space => KittyKey::Code(32),
+        NamedKey::Insert if numpad => KittyKey::Code(57425),
+        NamedKey::Insert => KittyKey::Tilde(2),
+        NamedKey::Delete if numpad => KittyKey::Code(57426),
+        NamedKey::Delete => KittyKey::Tilde(3),
+        NamedKey::ArrowLeft if numpad => KittyKey::Code(57417),
+        NamedKey::ArrowLeft => KittyKey::Letter('D'),
+        NamedKey::ArrowRight if numpad => KittyKey::Code(57418),
+        NamedKey::ArrowRight => KittyKey::Letter('C'),
+        NamedKey::ArrowUp if numpad => KittyKey::Code(57419),
+        NamedKey::ArrowUp => KittyKey::Letter('A'),
+        NamedKey::ArrowDown if numpad => KittyKey::Code(57420),
+        NamedKey::ArrowDown => KittyKey::Letter('B'),
+        NamedKey::PageUp if numpad => KittyKey::Code(57421),
+        NamedKey::PageUp => KittyKey::Tilde(5),
+        NamedKey::PageDown if numpad => KittyKey::Code(57422),
+        NamedKey::PageDown => KittyKey::Tilde(6),
+        NamedKey::Home if numpad => KittyKey::Code(57423),
+        NamedKey::Home => KittyKey::Letter('H'),
+        NamedKey::End if numpad => KittyKey::Code(57424),
+        NamedKey::End => KittyKey::Letter('F'),
+        NamedKey::CapsLock => KittyKey::Code(57358),
+        NamedKey::ScrollLock => KittyKey::Code(57359),
+        NamedKey::NumLock => KittyKey::Code(57360),
+        NamedKey::PrintScreen => KittyKey::Code(57361),
+        NamedKey::Pause => KittyKey::Code(57362),
+        NamedKey::ContextMenu => KittyKey::Code(57363),
+        NamedKey::F1 => KittyKey::Letter('P'),
+        NamedKey::F2 => KittyKey::Letter('Q'),
+        // CSI R is taken by cursor position reports
+        NamedKey::F3 => KittyKey::Tilde(13),
+        NamedKey::F4 => KittyKey::Letter('S'),
+        NamedKey::F5 => KittyKey::Tilde(15),
+        NamedKey::F6 => KittyKey::Tilde(17),
+        NamedKey::F7 => KittyKey::Tilde(18),
+        NamedKey::F8 => KittyKey::Tilde(19),
+        NamedKey::F9 => KittyKey::Tilde(20),
+        NamedKey::F10 => KittyKey::Tilde(21),
+        NamedKey::F11 => KittyKey::Tilde(23),
+        NamedKey::F12 => KittyKey::Tilde(24),
+        NamedKey::F13 => KittyKey::Code(57376),
+        NamedKey::F14 => KittyKey::Code(57377),
+        NamedKey::F15 => KittyKey::Code(57378),
+        NamedKey::F16 => KittyKey::Code(57379),
+        NamedKey::F17 => KittyKey::Code(57380),
+        NamedKey::F18 => KittyKey::Code(57381),
+        NamedKey::F19 => KittyKey::Code(57382),
+        NamedKey::F20 => KittyKey::Code(57383),
+        NamedKey::Shift => KittyKey::Code(if left { 57441 } else { 57447 }),
+        NamedKey::Control => KittyKey::Code(if left { 57442 } else { 57448 }),
+        NamedKey::Alt => KittyKey::Code(if left { 57443 } else { 57449 }),
+        NamedKey::Super => KittyKey::Code(if left { 57444 } else { 57450 }),
+        NamedKey::Hyper => KittyKey::Code(if left { 57445 } else { 57451 }),
+        NamedKey::Meta => KittyKey::Code(if left { 57446 } else { 57452 }),
+        _ => return None,
+    };
+    return Some(kitty_key);
+}
+
+fn keypad_code(chr: char) -> Option<u32> {
+    match chr {
+        '0'..='9' => return Some(57399 + chr as u32 - '0' as u32),
+        '.' => return Some(57409),
+        '/' => return Some(57410),
+        '*' => return Some(57411),
+        '-' => return Some(57412),
+        '+' => return Some(57413),
+        '=' => return Some(57415),
+        _ => return None,
+    }
+}
+
+fn is_modifier(key: &Key) -> bool {
+    return matches!(key, Key::Named(NamedKey::Shift | NamedKey::Control
+                                    | NamedKey::Alt | NamedKey::Super
+                                    | NamedKey::Hyper | NamedKey::Meta));
+}
+
+// unmodified is the key with no modifiers applied, this gives the
+// key code so shift+a reports 'a' with the shift modifier
+#[allow(clippy::too_many_arguments)]
+pub fn encode(key: &Key, unmodified: &Key, location: KeyLocation,
+              text: Option<&str>, mods: ModifiersState, event: KeyEventType,
+              flags: u8, modes: KeyModes) -> Option<Vec<u8>> {
+    let report_all = flags & REPORT_ALL_KEYS != 0;
+    let report_events = flags & REPORT_EVENTS != 0;
+
+    // without event reporting repeats are plain presses
+    let event = if report_events {
+        event
+    } else if event == KeyEventType::Release {
+        return None;
+    } else {
+        KeyEventType::Press
+    };
+
+    let kitty_key = match unmodified {
+        Key::Named(named) => named_key(*named, location)?,
+        Key::Character(chars) => {
+            let chr = chars.chars().next()?;
+            match keypad_code(chr) {
+                Some(code) if report_all && location == KeyLocation::Numpad =>
+                    KittyKey::Code(code),
+                _ => KittyKey::Code(chr.to_lowercase().next()? as u32),
+            }
+        }
+        _ => return None,
+    };
+
+    // modifiers on their own only get reported when asked for
+    if is_modifier(unmodified) && !report_all {
+        return None;
+    }
+
+    let text_mods = (mods - ModifiersState::SHIFT).is_empty();
+    if !report_all {
+        match (unmodified, event) {
+            // enter, tab and backspace stay usable in shells
+            (Key::Named(NamedKey::Enter | NamedKey::Tab | NamedKey::Backspace),
+             KeyEventType::Release) => return None,
+            (Key::Named(NamedKey::Enter | NamedKey::Tab | NamedKey::Backspace),
+             _) if mods.is_empty() => {
+                return keys::encode(key, location, text, mods, modes);
+            }
+            // text is sent as text when only shift is held
+            (Key::Character(_) | Key::Named(NamedKey::Space),
+             KeyEventType::Press | KeyEventType::Repeat) if text_mods => {
+                return keys::encode(key, location, text, mods, modes);
+            }
+            _ => {}
+        }
+    }
+
+    // modifiers ; event-type
+    let param = modifier_param(mods);
+    let mut mod_field = String::new();
+    if event != KeyEventType::Press {
+        mod_field = format!("{}:{}", param, event as u8);
+    } else if param > 1 {
+        mod_field = format!("{}", param);
+    }
+
+    match kitty_key {
+        KittyKey::Letter(fin) => {
+            if mod_field.is_empty() {
+                return Some(format!("\x1b[{}", fin).into_bytes());
+            }
+            return Some(format!("\x1b[1;{}{}", mod_field, fin).into_bytes());
+        }
+        KittyKey::Tilde(number) => {
+            if mod_field.is_empty() {
+                return Some(format!("\x1b[{}~", number).into_bytes());
+            }
+            return Some(format!("\x1b[{};{}~", number, mod_field).into_bytes());
+        }
+        KittyKey::Code(code) => {
+            let mut seq = format!("\x1b[{}", code);
+
+            // shifted key from the logical key, the base layout key
+            // is left out as winit doesn't give us the layout
+            if flags & REPORT_ALTERNATES != 0 && mods.shift_key() {
+                if let Key::Character(shifted) = key {
+                    if let Some(shifted) = shifted.chars().next() {
+                        if shifted as u32 != code {
+                            seq.push_str(&format!(":{}", shifted as u32));
+                        }
+                    }
+                }
+            }
+
+            // associated text as code points
+            let mut text_field = String::new();
+            if report_all && flags & REPORT_TEXT != 0
+                && event != KeyEventType::Release {
+                if let Some(text) = text {
+                    text_field = text.chars()
+                        .filter(|c| !c.is_control())
+                        .map(|c| (c as u32).to_string())
+                        .collect::<Vec<String>>()
+                        .join(":");
+                }
+            }
+
+            if !text_field.is_empty() {
+                if mod_field.is_empty() {
+                    mod_field = "1".to_string();
+                }
+                seq.push_str(&format!(";{};{}", mod_field, text_field));
+            } else if !mod_field.is_empty() {
+                seq.push_str(&format!(";{}", mod_field));
+            }
+            seq.push('u');
+            return Some(seq.into_bytes());
+        }
+    }
+}
@@ -0,0 +1,15 @@
+#[path ="parser.rs"]
+pub mod parser;
+#[path ="grid.rs"]
+pub mod grid;
+#[path ="term.rs"]
+pub mod term;
+#[path ="keys.rs"]
+pub mod keys;
+#[path ="kitty.rs"]
+pub mod kitty;
+#[path ="scrollback.rs"]
+pub mod scrollback;
+
+#[path ="color.rs"]
+pub mod color;
\ No newline at end of file
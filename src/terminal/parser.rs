@@ -0,0 +1,421 @@
+/*
+ *  parser for the output of the pty
+ *  this follows the DEC ANSI compatible state machine from
+ *  https://vt100.net/emu/dec_ansi_parser
+ *
+ *  the parser is fed chars instead of raw bytes, the pty reader
+ *  deals with utf-8 decoding so we only see code points here.
+ *  C1 controls (0x80 - 0x9f) are treated the same as their ESC forms.
+ *
+ *  instead of calling into the terminal for every action we hand back
+ *  a list of typed actions that the rest of the emulator consumes.
+ */
+
+// more than this and the sequence is flagged as ignored
+const MAX_INTERMEDIATES: usize = 2;
+const MAX_PARAMS: usize = 32;
+// an osc longer than this is cut short and dispatched truncated
+const MAX_OSC: usize = 1024;
+
+// each param can hold colon seperated subparams i.e. 38:2:r:g:b
+pub type Params = Vec<Vec<u16>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParseState {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiEntry,
+    CsiParam,
+    CsiIntermediate,
+    CsiIgnore,
+    DcsEntry,
+    DcsParam,
+    DcsIntermediate,
+    DcsPassthrough,
+    DcsIgnore,
+    OscString,
+    SosPmApcString,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    // draw a char at the cursor
+    Print(char),
+    // C0 or C1 control function
+    Execute(char),
+    CsiDispatch {
+        params: Params,
+        intermediates: Vec<char>,
+        ignore: bool,
+        action: char,
+    },
+    EscDispatch {
+        intermediates: Vec<char>,
+        ignore: bool,
+        action: char,
+    },
+    // osc params are split on ';'
+    OscDispatch(Vec<String>),
+    DcsHook {
+        params: Params,
+        intermediates: Vec<char>,
+        ignore: bool,
+        action: char,
+    },
+    DcsPut(char),
+    DcsUnhook,
+}
+
+pub struct Parser {
+    state: ParseState,
+    intermediates: Vec<char>,
+    params: Params,
+    // subparams of the param being read
+    param: Vec<u16>,
+    value: u16,
+    // set when any param char has been seen
+    param_started: bool,
+    ignoring: bool,
+    osc: String,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+// helpers for the ranges in the state diagram
+fn is_execute(chr: char) -> bool {
+    return matches!(chr, '\x00'..='\x17' | '\x19' | '\x1c'..='\x1f');
+}
+
+fn is_intermediate(chr: char) -> bool {
+    return matches!(chr, '\x20'..='\x2f');
+}
+
+fn is_param(chr: char) -> bool {
+    return matches!(chr, '0'..='9' | ':' | ';');
+}
+
+fn is_private_marker(chr: char) -> bool {
+    return matches!(chr, '\x3c'..='\x3f');
+}
+
+fn is_final(chr: char) -> bool {
+    return matches!(chr, '\x40'..='\x7e');
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        return Self {
+            state: ParseState::Ground,
+            intermediates: Vec::new(),
+            params: Vec::new(),
+            param: Vec::new(),
+            value: 0,
+            param_started: false,
+            ignoring: false,
+            osc: String::new(),
+        };
+    }
+
+    // parse a whole chunk of pty output
+    pub fn parse(&mut self, input: &str) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for chr in input.chars() {
+            self.advance(chr, &mut actions);
+        }
+        return actions;
+    }
+
+    // feeds a single char through the state machine
+    pub fn advance(&mut self, chr: char, actions: &mut Vec<Action>) {
+        // transitions from anywhere take priority
+        match chr {
+            '\x18' | '\x1a' => {
+                self.transition(ParseState::Ground, actions);
+                actions.push(Action::Execute(chr));
+                return;
+            }
+            '\x1b' => {
+                self.transition(ParseState::Escape, actions);
+                return;
+            }
+            '\u{90}' => {
+                self.transition(ParseState::DcsEntry, actions);
+                return;
+            }
+            '\u{9b}' => {
+                self.transition(ParseState::CsiEntry, actions);
+                return;
+            }
+            '\u{9c}' => {
+                self.transition(ParseState::Ground, actions);
+                return;
+            }
+            '\u{9d}' => {
+                self.transition(ParseState::OscString, actions);
+                return;
+            }
+            '\u{98}' | '\u{9e}' | '\u{9f}' => {
+                self.transition(ParseState::SosPmApcString, actions);
+                return;
+            }
+            '\u{80}'..='\u{9f}' => {
+                self.transition(ParseState::Ground, actions);
+                actions.push(Action::Execute(chr));
+                return;
+            }
+            _ => {}
+        }
+
+        match self.state {
+            ParseState::Ground => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if chr != '\x7f' {
+                    actions.push(Action::Print(chr));
+                }
+            }
+            ParseState::Escape => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::EscapeIntermediate;
+                } else {
+                    match chr {
+                        '[' => self.transition(ParseState::CsiEntry, actions),
+                        ']' => self.transition(ParseState::OscString, actions),
+                        'P' => self.transition(ParseState::DcsEntry, actions),
+                        'X' | '^' | '_' =>
+                            self.transition(ParseState::SosPmApcString, actions),
+                        // ST, the string it ends was dispatched when the
+                        // ESC left it so there is nothing more to do
+                        '\\' => self.transition(ParseState::Ground, actions),
+                        '\x30'..='\x7e' => {
+                            self.esc_dispatch(chr, actions);
+                            self.transition(ParseState::Ground, actions);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ParseState::EscapeIntermediate => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                } else if matches!(chr, '\x30'..='\x7e') {
+                    self.esc_dispatch(chr, actions);
+                    self.transition(ParseState::Ground, actions);
+                }
+            }
+            ParseState::CsiEntry => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::CsiIntermediate;
+                } else if is_param(chr) {
+                    self.param(chr);
+                    self.state = ParseState::CsiParam;
+                } else if is_private_marker(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::CsiParam;
+                } else if is_final(chr) {
+                    self.csi_dispatch(chr, actions);
+                    self.transition(ParseState::Ground, actions);
+                }
+            }
+            ParseState::CsiParam => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_param(chr) {
+                    self.param(chr);
+                } else if is_private_marker(chr) {
+                    self.state = ParseState::CsiIgnore;
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::CsiIntermediate;
+                } else if is_final(chr) {
+                    self.csi_dispatch(chr, actions);
+                    self.transition(ParseState::Ground, actions);
+                }
+            }
+            ParseState::CsiIntermediate => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                } else if matches!(chr, '\x30'..='\x3f') {
+                    self.state = ParseState::CsiIgnore;
+                } else if is_final(chr) {
+                    self.csi_dispatch(chr, actions);
+                    self.transition(ParseState::Ground, actions);
+                }
+            }
+            ParseState::CsiIgnore => {
+                if is_execute(chr) {
+                    actions.push(Action::Execute(chr));
+                } else if is_final(chr) {
+                    self.transition(ParseState::Ground, actions);
+                }
+            }
+            ParseState::DcsEntry => {
+                if is_intermediate(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::DcsIntermediate;
+                } else if is_param(chr) {
+                    self.param(chr);
+                    self.state = ParseState::DcsParam;
+                } else if is_private_marker(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::DcsParam;
+                } else if is_final(chr) {
+                    self.dcs_hook(chr, actions);
+                }
+            }
+            ParseState::DcsParam => {
+                if is_param(chr) {
+                    self.param(chr);
+                } else if is_private_marker(chr) {
+                    self.state = ParseState::DcsIgnore;
+                } else if is_intermediate(chr) {
+                    self.collect(chr);
+                    self.state = ParseState::DcsIntermediate;
+                } else if is_final(chr) {
+                    self.dcs_hook(chr, actions);
+                }
+            }
+            ParseState::DcsIntermediate => {
+                if is_intermediate(chr) {
+                    self.collect(chr);
+                } else if matches!(chr, '\x30'..='\x3f') {
+                    self.state = ParseState::DcsIgnore;
+                } else if is_final(chr) {
+                    self.dcs_hook(chr, actions);
+                }
+            }
+            ParseState::DcsPassthrough => {
+                if chr != '\x7f' {
+                    actions.push(Action::DcsPut(chr));
+                }
+            }
+            ParseState::OscString => {
+                // xterm allows BEL to end an osc as well as ST
+                if chr == '\x07' {
+                    self.transition(ParseState::Ground, actions);
+                } else if !is_execute(chr) && self.osc.len() < MAX_OSC {
+                    self.osc.push(chr);
+                }
+            }
+            ParseState::DcsIgnore | ParseState::SosPmApcString => {}
+        }
+    }
+
+    // runs the exit action of the old state and the entry action of the new
+    fn transition(&mut self, next: ParseState, actions: &mut Vec<Action>) {
+        match self.state {
+            ParseState::OscString => {
+                let osc = std::mem::take(&mut self.osc);
+                actions.push(Action::OscDispatch(
+                    osc.split(';').map(|s| s.to_string()).collect()));
+            }
+            ParseState::DcsPassthrough => actions.push(Action::DcsUnhook),
+            _ => {}
+        }
+
+        match next {
+            ParseState::Escape | ParseState::CsiEntry | ParseState::DcsEntry =>
+                self.clear(),
+            ParseState::OscString => self.osc.clear(),
+            _ => {}
+        }
+
+        self.state = next;
+    }
+
+    fn clear(&mut self) {
+        self.intermediates.clear();
+        self.params.clear();
+        self.param.clear();
+        self.value = 0;
+        self.param_started = false;
+        self.ignoring = false;
+    }
+
+    fn collect(&mut self, chr: char) {
+        if self.intermediates.len() < MAX_INTERMEDIATES {
+            self.intermediates.push(chr);
+        } else {
+            self.ignoring = true;
+        }
+    }
+
+    fn param(&mut self, chr: char) {
+        self.param_started = true;
+        if self.params.len() >= MAX_PARAMS {
+            self.ignoring = true;
+            return;
+        }
+
+        match chr {
+            ';' => {
+                self.param.push(self.value);
+                self.params.push(std::mem::take(&mut self.param));
+                self.value = 0;
+            }
+            ':' => {
+                self.param.push(self.value);
+                self.value = 0;
+            }
+            _ => {
+                let digit = chr as u16 - '0' as u16;
+                self.value = self.value.saturating_mul(10).saturating_add(digit);
+            }
+        }
+    }
+
+    // push the last param which has no trailing ';'
+    fn take_params(&mut self) -> Params {
+        if self.param_started && self.params.len() < MAX_PARAMS {
+            self.param.push(self.value);
+            self.params.push(std::mem::take(&mut self.param));
+        }
+        self.value = 0;
+        self.param_started = false;
+        return std::mem::take(&mut self.params);
+    }
+
+    fn csi_dispatch(&mut self, action: char, actions: &mut Vec<Action>) {
+        let params = self.take_params();
+        actions.push(Action::CsiDispatch {
+            params,
+            intermediates: self.intermediates.clone(),
+            ignore: self.ignoring,
+            action
+        });
+    }
+
+    fn esc_dispatch(&mut self, action: char, actions: &mut Vec<Action>) {
+        actions.push(Action::EscDispatch {
+            intermediates: self.intermediates.clone(),
+            ignore: self.ignoring,
+            action
+        });
+    }
+
+    fn dcs_hook(&mut self, action: char, actions: &mut Vec<Action>) {
+        let params = self.take_params();
+        actions.push(Action::DcsHook {
+            params,
+            intermediates: self.intermediates.clone(),
+            ignore: self.ignoring,
+            action
+        });
+        self.state = ParseState::DcsPassthrough;
+    }
+}
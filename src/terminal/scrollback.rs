@@ -0,0 +1,49 @@
+/*
+ *  history of the lines that scrolled off the top of the screen
+ *  kept as a ring buffer so old lines drop off once the limit is hit
+ */
+use crate::terminal::grid::Cell;
+use std::collections::VecDeque;
+
+pub struct Scrollback {
+    lines: VecDeque<Vec<Cell>>,
+    // max number of lines kept, 0 turns history off
+    limit: usize,
+}
+
+impl Scrollback {
+    pub fn new(limit: usize) -> Self {
+        return Self { lines: VecDeque::new(), limit };
+    }
+
+    pub fn push(&mut self, line: Vec<Cell>) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.lines.len() >= self.limit {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn limit(&self) -> usize {
+        return self.limit;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.lines.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.lines.is_empty();
+    }
+
+    // 0 is the oldest line
+    pub fn line(&self, idx: usize) -> &[Cell] {
+        return &self.lines[idx];
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
@@ -0,0 +1,710 @@
+/*
+ *  terminal state machine
+ *  takes the actions from the parser and runs them against the grid
+ *  control functions follow xterm's ctlseqs where they differ from the vt100
+ */
+use crate::terminal::grid::{Cell, Color, Cursor, Grid, ATTR_BLINK, ATTR_BOLD,
+                            ATTR_DIM, ATTR_HIDDEN, ATTR_ITALIC, ATTR_REVERSE,
+                            ATTR_STRIKE, ATTR_UNDERLINE};
+use crate::terminal::keys::KeyModes;
+use crate::terminal::kitty::KittyFlags;
+use crate::terminal::parser::{Action, Params, Parser};
+use crate::terminal::scrollback::Scrollback;
+
+use unicode_width::UnicodeWidthChar;
+
+const TAB_WIDTH: usize = 8;
+
+// gets param idx, zero or missing params take the default
+fn param_or(params: &Params, idx: usize, default: u16) -> u16 {
+    match params.get(idx).and_then(|p| p.first()) {
+        Some(&0) | None => return default,
+        Some(&value) => return value,
+    }
+}
+
+// how the cursor is drawn, set by DECSCUSR
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blinking: bool,
+}
+
+impl Default for CursorStyle {
+    fn default() -> Self {
+        return Self { shape: CursorShape::Block, blinking: true };
+    }
+}
+
+impl CursorStyle {
+    // DECSCUSR param, 0 and 1 are a blinking block, then each shape
+    // blinking and steady in turn
+    fn from_param(param: u16) -> Option<Self> {
+        let shape = match param {
+            0..=2 => CursorShape::Block,
+            3 | 4 => CursorShape::Underline,
+            5 | 6 => CursorShape::Bar,
+            _ => return None,
+        };
+        return Some(Self { shape, blinking: param == 0 || param % 2 == 1 });
+    }
+}
+
+// the state kept separately by the primary and alternate screens
+struct Screen {
+    grid: Grid,
+    saved_cursor: Option<(Cursor, Cell)>,
+    kitty_flags: KittyFlags,
+}
+
+// the colour after 38 or 48, either as subparams 38:5:n and 38:2::r:g:b
+// or as the params that follow 38;5;n and 38;2;r;g;b
+fn extended_color(param: &[u16], params: &mut std::slice::Iter<Vec<u16>>)
+    -> Option<Color> {
+    let channel = |v: u16| v.min(255) as u8;
+    if param.len() > 1 {
+        match param[1] {
+            5 => return param.get(2).map(|&idx| Color::Indexed(channel(idx))),
+            2 => {
+                // the colour space id before r:g:b is optional
+                let rgb = if param.len() > 5 { &param[3..6] } else { &param[2..] };
+                if rgb.len() < 3 {
+                    return None;
+                }
+                return Some(Color::Rgb(channel(rgb[0]), channel(rgb[1]),
+                                       channel(rgb[2])));
+            }
+            _ => return None,
+        }
+    }
+
+    let mut next = || params.next().and_then(|p| p.first().copied());
+    match next()? {
+        5 => return Some(Color::Indexed(channel(next()?))),
+        2 => {
+            let (r, g, b) = (next()?, next()?, next()?);
+            return Some(Color::Rgb(channel(r), channel(g), channel(b)));
+        }
+        _ => return None,
+    }
+}
+
+pub struct Term {
+    // the screen being shown, the other one is kept in inactive
+    pub grid: Grid,
+    inactive: Screen,
+    // full screen apps draw on the alternate screen which has no history
+    pub alt_screen: bool,
+    pub scrollback: Scrollback,
+    // how many lines the view is scrolled back into history, 0 is live
+    pub display_offset: usize,
+    // the whole view needs redrawing, set when it moves or changes screen
+    full_damage: bool,
+    pub title: Option<String>,
+    parser: Parser,
+    // new chars are drawn with the attributes of this cell
+    template: Cell,
+    // DECSC saves the cursor and its attributes
+    saved_cursor: Option<(Cursor, Cell)>,
+    // inclusive scroll region set by DECSTBM
+    scroll_top: usize,
+    scroll_bottom: usize,
+    // set after printing in the last column, the wrap happens
+    // on the next print so that the cursor stays on the line
+    wrap_pending: bool,
+    autowrap: bool,
+    tabstops: Vec<bool>,
+    // changes what the cursor keys and keypad send
+    pub key_modes: KeyModes,
+    pub kitty_flags: KittyFlags,
+    // replies to queries, written back to the pty by the app
+    pub responses: Vec<u8>,
+    pub cursor_style: CursorStyle,
+    // DECTCEM, apps hide the cursor while they draw
+    pub cursor_visible: bool,
+}
+
+impl Term {
+    // the grid is at least 1x1 whatever size is asked for
+    pub fn new(rows: usize, cols: usize, scrollback_lines: usize) -> Self {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        return Self {
+            grid: Grid::new(rows, cols),
+            inactive: Screen {
+                grid: Grid::new(rows, cols),
+                saved_cursor: None,
+                kitty_flags: KittyFlags::default(),
+            },
+            alt_screen: false,
+            scrollback: Scrollback::new(scrollback_lines),
+            display_offset: 0,
+            full_damage: true,
+            title: None,
+            parser: Parser::new(),
+            template: Cell::default(),
+            saved_cursor: None,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            wrap_pending: false,
+            autowrap: true,
+            tabstops: (0..cols).map(|col| col % TAB_WIDTH == 0).collect(),
+            key_modes: KeyModes::default(),
+            kitty_flags: KittyFlags::default(),
+            responses: Vec::new(),
+            cursor_style: CursorStyle::default(),
+            cursor_visible: true,
+        };
+    }
+
+    // parses pty output and applies it to the screen
+    pub fn advance(&mut self, text: &str) {
+        let actions = self.parser.parse(text);
+        self.apply(actions);
+    }
+
+    pub fn apply(&mut self, actions: Vec<Action>) {
+        for action in actions {
+            match action {
+                Action::Print(chr) => self.print(chr),
+                Action::Execute(chr) => self.execute(chr),
+                Action::CsiDispatch { params, intermediates, ignore, action } => {
+                    if !ignore {
+                        self.csi_dispatch(&params, &intermediates, action);
+                    }
+                }
+                Action::EscDispatch { intermediates, ignore, action } => {
+                    if !ignore {
+                        self.esc_dispatch(&intermediates, action);
+                    }
+                }
+                Action::OscDispatch(params) => self.osc_dispatch(&params),
+                // no device control strings are supported, their payload
+                // (sixel, DECRQSS, tmux passthrough) is dropped
+                Action::DcsHook { .. } | Action::DcsPut(_) | Action::DcsUnhook => {}
+            }
+        }
+    }
+
+    // width comes from the unicode east asian width tables, wide chars
+    // take two cells and zero width ones are marks on the cell before
+    fn print(&mut self, chr: char) {
+        let width = chr.width().unwrap_or(1);
+        if width == 0 {
+            self.print_mark(chr);
+            return;
+        }
+        if self.wrap_pending && self.autowrap {
+            self.grid.cursor.col = 0;
+            self.linefeed();
+        }
+        self.wrap_pending = false;
+
+        // a wide char that doesn't fit in the line goes on the next one
+        let wide = width == 2 && self.grid.cols > 1;
+        if wide && self.grid.cursor.col + 2 > self.grid.cols {
+            let Cursor { row, col } = self.grid.cursor;
+            if self.autowrap {
+                self.grid.split_wide(row, col, &self.template);
+                *self.grid.cell_mut(row, col) = Cell::blank(&self.template);
+                self.grid.cursor.col = 0;
+                self.linefeed();
+            } else {
+                self.grid.cursor.col = self.grid.cols - 2;
+            }
+        }
+
+        let Cursor { row, col } = self.grid.cursor;
+        self.grid.split_wide(row, col, &self.template);
+        *self.grid.cell_mut(row, col) = Cell { c: chr, ..self.template };
+        let mut last = col;
+        if wide {
+            last += 1;
+            self.grid.split_wide(row, last, &self.template);
+            self.grid.cell_mut(row, col).width = 2;
+            *self.grid.cell_mut(row, last) = Cell::spacer(&self.template);
+        }
+
+        if last + 1 >= self.grid.cols {
+            self.grid.cursor.col = last;
+            self.wrap_pending = true;
+        } else {
+            self.grid.cursor.col = last + 1;
+        }
+    }
+
+    // attaches a combining mark to the char before the cursor, marks with
+    // nothing before them on the line are dropped
+    fn print_mark(&mut self, mark: char) {
+        let Cursor { row, col } = self.grid.cursor;
+        // the cursor stays on the last char when a wrap is pending
+        let Some(mut col) = (if self.wrap_pending { Some(col) }
+                             else { col.checked_sub(1) }) else { return };
+        if self.grid.cell(row, col).is_spacer() && col > 0 {
+            col -= 1;
+        }
+        self.grid.cell_mut(row, col).push_mark(mark);
+    }
+
+    fn execute(&mut self, chr: char) {
+        match chr {
+            // BS
+            '\x08' => {
+                self.grid.cursor.col = self.grid.cursor.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            // HT
+            '\t' => self.tab(),
+            // LF, VT, FF
+            '\n' | '\x0b' | '\x0c' => self.linefeed(),
+            // CR
+            '\r' => {
+                self.grid.cursor.col = 0;
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn tab(&mut self) {
+        let mut col = self.grid.cursor.col + 1;
+        while col < self.grid.cols - 1 && !self.tabstops[col] {
+            col += 1;
+        }
+        self.grid.cursor.col = col.min(self.grid.cols - 1);
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.grid.cursor.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.grid.cursor.row + 1 < self.grid.rows {
+            self.grid.cursor.row += 1;
+        }
+    }
+
+    // scrolls the scroll region, lines leaving the top of the
+    // primary screen go into the scrollback
+    fn scroll_up(&mut self, n: usize) {
+        let scrolled = self.grid.scroll_up(self.scroll_top, self.scroll_bottom,
+                                           n, &self.template);
+        if self.scroll_top == 0 && !self.alt_screen {
+            self.push_history(scrolled);
+        }
+    }
+
+    fn push_history(&mut self, lines: Vec<Vec<Cell>>) {
+        for line in lines {
+            self.scrollback.push(line);
+            // keep the view on the same lines while scrolled back
+            if self.display_offset > 0 {
+                self.display_offset =
+                    (self.display_offset + 1).min(self.scrollback.len());
+            }
+        }
+    }
+
+    // moves the view into the history, positive deltas go back
+    pub fn scroll_display(&mut self, delta: isize) {
+        if self.alt_screen {
+            return;
+        }
+        let offset = self.display_offset as isize + delta;
+        let offset = offset.clamp(0, self.scrollback.len() as isize) as usize;
+        self.full_damage |= offset != self.display_offset;
+        self.display_offset = offset;
+    }
+
+    // snaps the view back to the live screen
+    pub fn reset_display(&mut self) {
+        self.full_damage |= self.display_offset != 0;
+        self.display_offset = 0;
+    }
+
+    // rows of the view that changed since the last call
+    pub fn take_damage(&mut self) -> Vec<bool> {
+        let dirty = self.grid.take_dirty();
+        // while scrolled back the screen lines sit lower in the view
+        if self.full_damage || (self.display_offset > 0 && dirty.contains(&true)) {
+            self.full_damage = false;
+            return vec![true; self.grid.rows];
+        }
+        return dirty;
+    }
+
+    // row of the view which mixes history and the screen
+    // history lines may be a different width to the screen
+    pub fn display_row(&self, row: usize) -> &[Cell] {
+        if row < self.display_offset {
+            let idx = self.scrollback.len() - self.display_offset + row;
+            return self.scrollback.line(idx);
+        }
+        return self.grid.row(row - self.display_offset);
+    }
+
+    fn reverse_index(&mut self) {
+        self.wrap_pending = false;
+        if self.grid.cursor.row == self.scroll_top {
+            self.grid.scroll_down(self.scroll_top, self.scroll_bottom, 1,
+                                  &self.template);
+        } else if self.grid.cursor.row > 0 {
+            self.grid.cursor.row -= 1;
+        }
+    }
+
+    // moves the cursor clamped to the screen
+    fn goto(&mut self, row: usize, col: usize) {
+        self.grid.cursor.row = row.min(self.grid.rows - 1);
+        self.grid.cursor.col = col.min(self.grid.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some((self.grid.cursor, self.template));
+    }
+
+    fn restore_cursor(&mut self) {
+        let (cursor, template) =
+            self.saved_cursor.unwrap_or((Cursor::default(), Cell::default()));
+        self.template = template;
+        self.goto(cursor.row, cursor.col);
+    }
+
+    // swaps the primary and alternate screens
+    // the cursor position carries over like in xterm
+    fn swap_screen(&mut self) {
+        let cursor = self.grid.cursor;
+        std::mem::swap(&mut self.grid, &mut self.inactive.grid);
+        std::mem::swap(&mut self.saved_cursor, &mut self.inactive.saved_cursor);
+        std::mem::swap(&mut self.kitty_flags, &mut self.inactive.kitty_flags);
+        self.alt_screen = !self.alt_screen;
+        self.display_offset = 0;
+        self.full_damage = true;
+        self.goto(cursor.row, cursor.col);
+    }
+
+    // modes 47, 1047 and 1049
+    // 1047 clears the alternate screen on the way out, 1049 clears it on
+    // the way in and saves the cursor of the primary screen around it
+    fn set_alt_screen(&mut self, mode: u16, enable: bool) {
+        if enable == self.alt_screen {
+            return;
+        }
+        if enable {
+            if mode == 1049 {
+                self.save_cursor();
+            }
+            self.swap_screen();
+            if mode == 1049 {
+                self.grid.clear_rows(0, self.grid.rows, &self.template);
+            }
+        } else {
+            if mode == 1047 {
+                self.grid.clear_rows(0, self.grid.rows, &self.template);
+            }
+            self.swap_screen();
+            if mode == 1049 {
+                self.restore_cursor();
+            }
+        }
+    }
+
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let dropped = self.grid.resize(rows, cols);
+        let inactive_dropped = self.inactive.grid.resize(rows, cols);
+        // only lines from the primary screen are kept
+        if self.alt_screen {
+            self.push_history(inactive_dropped);
+        } else {
+            self.push_history(dropped);
+        }
+        self.display_offset = self.display_offset.min(self.scrollback.len());
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.wrap_pending = false;
+
+        // keep the stops that were set, new cols get the defaults
+        let old_cols = self.tabstops.len();
+        self.tabstops.resize(cols, false);
+        for col in old_cols..cols {
+            self.tabstops[col] = col % TAB_WIDTH == 0;
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.grid.rows, self.grid.cols, self.scrollback.limit());
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[char],
+                    action: char) {
+        let Cursor { row, col } = self.grid.cursor;
+        let n = param_or(params, 0, 1) as usize;
+
+        match (intermediates, action) {
+            // ICH
+            ([], '@') => self.grid.insert_blanks(row, col, n, &self.template),
+            // CUU
+            ([], 'A') => self.goto(row.saturating_sub(n), col),
+            // CUD
+            ([], 'B') | ([], 'e') => self.goto(row + n, col),
+            // CUF
+            ([], 'C') | ([], 'a') => self.goto(row, col + n),
+            // CUB
+            ([], 'D') => self.goto(row, col.saturating_sub(n)),
+            // CNL
+            ([], 'E') => self.goto(row + n, 0),
+            // CPL
+            ([], 'F') => self.goto(row.saturating_sub(n), 0),
+            // CHA
+            ([], 'G') | ([], '`') => self.goto(row, n - 1),
+            // CUP
+            ([], 'H') | ([], 'f') => {
+                let col = param_or(params, 1, 1) as usize;
+                self.goto(n - 1, col - 1);
+            }
+            // ED
+            ([], 'J') => {
+                let rows = self.grid.rows;
+                match param_or(params, 0, 0) {
+                    0 => {
+                        self.grid.clear_cols(row, col, self.grid.cols,
+                                             &self.template);
+                        self.grid.clear_rows(row + 1, rows, &self.template);
+                    }
+                    1 => {
+                        self.grid.clear_rows(0, row, &self.template);
+                        self.grid.clear_cols(row, 0, col + 1, &self.template);
+                    }
+                    2 => self.grid.clear_rows(0, rows, &self.template),
+                    // xterm's erase saved lines
+                    3 => {
+                        self.scrollback.clear();
+                        self.reset_display();
+                    }
+                    _ => {}
+                }
+            }
+            // EL
+            ([], 'K') => {
+                match param_or(params, 0, 0) {
+                    0 => self.grid.clear_cols(row, col, self.grid.cols,
+                                              &self.template),
+                    1 => self.grid.clear_cols(row, 0, col + 1, &self.template),
+                    2 => self.grid.clear_cols(row, 0, self.grid.cols,
+                                              &self.template),
+                    _ => {}
+                }
+            }
+            // IL
+            ([], 'L') => {
+                if (self.scroll_top..=self.scroll_bottom).contains(&row) {
+                    self.grid.scroll_down(row, self.scroll_bottom, n,
+                                          &self.template);
+                    self.goto(row, 0);
+                }
+            }
+            // DL
+            ([], 'M') => {
+                if (self.scroll_top..=self.scroll_bottom).contains(&row) {
+                    self.grid.scroll_up(row, self.scroll_bottom, n,
+                                        &self.template);
+                    self.goto(row, 0);
+                }
+            }
+            // DCH
+            ([], 'P') => self.grid.delete_cells(row, col, n, &self.template),
+            // SU
+            ([], 'S') => self.scroll_up(n),
+            // SD
+            ([], 'T') => self.grid.scroll_down(self.scroll_top,
+                                               self.scroll_bottom, n,
+                                               &self.template),
+            // ECH
+            ([], 'X') => self.grid.clear_cols(row, col, col + n, &self.template),
+            // VPA
+            ([], 'd') => self.goto(n - 1, col),
+            // TBC
+            ([], 'g') => {
+                match param_or(params, 0, 0) {
+                    0 => self.tabstops[col] = false,
+                    3 => self.tabstops.iter_mut().for_each(|t| *t = false),
+                    _ => {}
+                }
+            }
+            // DECSTBM
+            ([], 'r') => {
+                let top = n - 1;
+                let bottom = (param_or(params, 1, self.grid.rows as u16)
+                              as usize).min(self.grid.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            // SGR
+            ([], 'm') => self.sgr(params),
+            // SCOSC and SCORC
+            ([], 's') => self.save_cursor(),
+            ([], 'u') => self.restore_cursor(),
+            // kitty keyboard protocol query, push, pop and set
+            (['?'], 'u') => {
+                let reply = format!("\x1b[?{}u", self.kitty_flags.current());
+                self.responses.extend_from_slice(reply.as_bytes());
+            }
+            (['>'], 'u') => self.kitty_flags.push(param_or(params, 0, 0) as u8),
+            (['<'], 'u') => self.kitty_flags.pop(n),
+            (['='], 'u') => self.kitty_flags.set(param_or(params, 0, 0) as u8,
+                                                 param_or(params, 1, 1)),
+            // DECSCUSR
+            ([' '], 'q') => {
+                if let Some(style) = CursorStyle::from_param(param_or(params, 0, 0)) {
+                    self.cursor_style = style;
+                }
+            }
+            // DECSET and DECRST
+            (['?'], 'h') => self.set_private_modes(params, true),
+            (['?'], 'l') => self.set_private_modes(params, false),
+            _ => {
+                #[cfg(debug_assertions)]
+                println!("unhandled csi {:?} {:?} {}", params, intermediates,
+                         action);
+            }
+        }
+    }
+
+    // SGR, sets the attributes new chars are drawn with
+    fn sgr(&mut self, params: &Params) {
+        if params.is_empty() {
+            self.template = Cell::default();
+            return;
+        }
+
+        let mut params = params.iter();
+        while let Some(param) = params.next() {
+            let code = param.first().copied().unwrap_or(0);
+            let template = &mut self.template;
+            match code {
+                0 => *template = Cell::default(),
+                1 => template.attrs |= ATTR_BOLD,
+                2 => template.attrs |= ATTR_DIM,
+                3 => template.attrs |= ATTR_ITALIC,
+                // 4:0 turns underline off, the other styles are drawn plain
+                4 => match param.get(1) {
+                    Some(0) => template.attrs &= !ATTR_UNDERLINE,
+                    _ => template.attrs |= ATTR_UNDERLINE,
+                },
+                5 | 6 => template.attrs |= ATTR_BLINK,
+                7 => template.attrs |= ATTR_REVERSE,
+                8 => template.attrs |= ATTR_HIDDEN,
+                9 => template.attrs |= ATTR_STRIKE,
+                // double underline
+                21 => template.attrs |= ATTR_UNDERLINE,
+                22 => template.attrs &= !(ATTR_BOLD | ATTR_DIM),
+                23 => template.attrs &= !ATTR_ITALIC,
+                24 => template.attrs &= !ATTR_UNDERLINE,
+                25 => template.attrs &= !ATTR_BLINK,
+                27 => template.attrs &= !ATTR_REVERSE,
+                28 => template.attrs &= !ATTR_HIDDEN,
+                29 => template.attrs &= !ATTR_STRIKE,
+                30..=37 => template.fg = Color::Indexed((code - 30) as u8),
+                38 => {
+                    if let Some(color) = extended_color(param, &mut params) {
+                        template.fg = color;
+                    }
+                }
+                39 => template.fg = Color::Default,
+                40..=47 => template.bg = Color::Indexed((code - 40) as u8),
+                48 => {
+                    if let Some(color) = extended_color(param, &mut params) {
+                        template.bg = color;
+                    }
+                }
+                49 => template.bg = Color::Default,
+                90..=97 => template.fg = Color::Indexed((code - 90 + 8) as u8),
+                100..=107 => template.bg = Color::Indexed((code - 100 + 8) as u8),
+                _ => {
+                    #[cfg(debug_assertions)]
+                    println!("unhandled sgr {:?}", param);
+                }
+            }
+        }
+    }
+
+    fn set_private_modes(&mut self, params: &Params, enable: bool) {
+        for mode in params.iter().filter_map(|p| p.first()) {
+            match mode {
+                // DECCKM
+                1 => self.key_modes.app_cursor = enable,
+                // DECAWM
+                7 => self.autowrap = enable,
+                // start and stop blinking the cursor, as in xterm
+                12 => self.cursor_style.blinking = enable,
+                // DECTCEM
+                25 => self.cursor_visible = enable,
+                // alternate screen
+                47 | 1047 | 1049 => self.set_alt_screen(*mode, enable),
+                // save and restore the cursor as in DECSC and DECRC
+                1048 => {
+                    if enable {
+                        self.save_cursor();
+                    } else {
+                        self.restore_cursor();
+                    }
+                }
+                _ => {
+                    #[cfg(debug_assertions)]
+                    println!("unhandled private mode {} {}", mode, enable);
+                }
+            }
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[char], action: char) {
+        match (intermediates, action) {
+            // DECSC and DECRC
+            ([], '7') => self.save_cursor(),
+            ([], '8') => self.restore_cursor(),
+            // IND
+            ([], 'D') => self.linefeed(),
+            // NEL
+            ([], 'E') => {
+                self.grid.cursor.col = 0;
+                self.linefeed();
+            }
+            // DECKPAM and DECKPNM
+            ([], '=') => self.key_modes.app_keypad = true,
+            ([], '>') => self.key_modes.app_keypad = false,
+            // HTS
+            ([], 'H') => self.tabstops[self.grid.cursor.col] = true,
+            // RI
+            ([], 'M') => self.reverse_index(),
+            // RIS
+            ([], 'c') => self.reset(),
+            _ => {
+                #[cfg(debug_assertions)]
+                println!("unhandled esc {:?} {}", intermediates, action);
+            }
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[String]) {
+        match params.first().map(|s| s.as_str()) {
+            // set icon name and window title
+            Some("0") | Some("2") => {
+                self.title = Some(params[1..].join(";"));
+            }
+            _ => {
+                #[cfg(debug_assertions)]
+                println!("unhandled osc {:?}", params);
+            }
+        }
+    }
+}
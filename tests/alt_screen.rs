@@ -0,0 +1,77 @@
+use hermitshell::terminal::term::Term;
+
+fn screen(term: &Term) -> Vec<String> {
+    return (0..term.grid.rows)
+        .map(|row| term.grid.row(row).iter().map(|cell| cell.c).collect())
+        .collect();
+}
+
+fn cursor(term: &Term) -> (usize, usize) {
+    return (term.grid.cursor.row, term.grid.cursor.col);
+}
+
+#[test]
+fn mode_1049_restores_the_primary_screen() {
+    let mut term = Term::new(3, 4, 100);
+    term.advance("ab\r\ncd\x1b[1;2H");
+    term.advance("\x1b[?1049h");
+    assert!(term.alt_screen);
+    // the alternate screen starts clear
+    assert_eq!(screen(&term), ["    "; 3]);
+    term.advance("\x1b[3;3Hxyz");
+    term.advance("\x1b[?1049l");
+    assert!(!term.alt_screen);
+    assert_eq!(screen(&term), ["ab  ", "cd  ", "    "]);
+    // with the cursor where it was saved
+    assert_eq!(cursor(&term), (0, 1));
+}
+
+#[test]
+fn mode_1049_clears_the_alt_screen_each_time() {
+    let mut term = Term::new(2, 2, 100);
+    term.advance("\x1b[?1049hzz\x1b[?1049l\x1b[?1049h");
+    assert_eq!(screen(&term), ["  "; 2]);
+}
+
+#[test]
+fn mode_47_keeps_the_alt_screen() {
+    let mut term = Term::new(2, 2, 100);
+    term.advance("\x1b[?47hzz\x1b[?47lab\x1b[?47h");
+    assert_eq!(screen(&term)[0], "zz");
+    // 1047 clears it on the way out
+    term.advance("\x1b[?1047l\x1b[?1047h");
+    assert_eq!(screen(&term), ["  "; 2]);
+}
+
+#[test]
+fn mode_1048_saves_the_cursor() {
+    let mut term = Term::new(3, 3, 100);
+    term.advance("\x1b[2;3H\x1b[?1048h\x1b[H\x1b[?1048l");
+    assert_eq!(cursor(&term), (1, 2));
+}
+
+#[test]
+fn alt_screen_output_stays_out_of_scrollback() {
+    let mut term = Term::new(2, 2, 100);
+    term.advance("\x1b[?1049h");
+    term.advance(&"x\r\n".repeat(10));
+    term.advance("\x1b[S\x1b[3S");
+    assert!(term.scrollback.is_empty());
+    // and it can't be scrolled back through
+    term.scroll_display(5);
+    assert_eq!(term.display_offset, 0);
+    term.advance("\x1b[?1049l");
+    assert!(term.scrollback.is_empty());
+}
+
+#[test]
+fn ris_leaves_the_alt_screen() {
+    let mut term = Term::new(2, 3, 100);
+    term.advance("abc\x1b[?1049hxyz\x1bc");
+    assert!(!term.alt_screen);
+    assert_eq!(screen(&term), ["   "; 2]);
+    // and a later switch still works
+    term.advance("q\x1b[?1049h\x1b[?1049l");
+    assert!(!term.alt_screen);
+    assert_eq!(screen(&term)[0], "q  ");
+}
@@ -0,0 +1,47 @@
+use hermitshell::font_atlas::font_atlas::{Antialias, TermConfig};
+use hermitshell::parse_args;
+
+fn parse(args: &str) -> Result<TermConfig, String> {
+    return parse_args(args.split_whitespace().map(String::from));
+}
+
+#[test]
+fn defaults() {
+    let config = parse("mono.ttf").unwrap();
+    assert_eq!(config.font_dirs, ["mono.ttf"]);
+    assert_eq!(config.antialias, Antialias::Grayscale);
+    assert_eq!((config.gamma, config.contrast), (1.0, 0.0));
+    assert_eq!((config.line_height, config.letter_spacing), (1.0, 0.0));
+    assert_eq!(config.scrollback_lines, 10000);
+    assert!(config.bold_font.is_none());
+}
+
+#[test]
+fn flags() {
+    let config = parse("mono.ttf --antialias lcd --gamma 1.8 --contrast 0.5 \
+                        --line-height 1.2 --letter-spacing -1 --scrollback 0 \
+                        --bold bold.ttf cjk.ttc").unwrap();
+    assert_eq!(config.font_dirs, ["mono.ttf", "cjk.ttc"]);
+    assert_eq!(config.antialias, Antialias::Lcd);
+    assert_eq!((config.gamma, config.contrast), (1.8, 0.5));
+    assert_eq!((config.line_height, config.letter_spacing), (1.2, -1.0));
+    assert_eq!(config.scrollback_lines, 0);
+    assert_eq!(config.bold_font.as_deref(), Some("bold.ttf"));
+    assert_eq!(parse("mono.ttf --antialias grayscale").unwrap().antialias,
+               Antialias::Grayscale);
+}
+
+#[test]
+fn bad_values_are_rejected() {
+    for args in ["--antialias subpixel", "--antialias", "--gamma 0", "--gamma -1",
+                 "--gamma inf", "--gamma NaN", "--gamma", "--contrast -0.5",
+                 "--contrast x", "--line-height tall", "--scrollback -1"] {
+        assert!(parse(&format!("mono.ttf {}", args)).is_err(), "{}", args);
+    }
+}
+
+#[test]
+fn a_font_is_needed() {
+    assert!(parse("").is_err());
+    assert!(parse("--bold bold.ttf --gamma 2").is_err());
+}
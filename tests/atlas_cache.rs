@@ -0,0 +1,168 @@
+mod common;
+
+use hermitshell::font_atlas::cache::{self, CachedAtlas};
+use hermitshell::font_atlas::font_atlas::{Antialias, AtlasEntry, Face, FontAtlas, Glpyh,
+                                         PAGE_SIZE};
+use hermitshell::font_atlas::packer::Skyline;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn tmp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("hermitshell_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    return dir;
+}
+
+fn sample(antialias: Antialias) -> CachedAtlas {
+    let bytes_per_pixel = antialias.bytes_per_pixel();
+    let mut skyline = Skyline::new(PAGE_SIZE, PAGE_SIZE);
+    let mut lookup = HashMap::new();
+    let mut pixels = vec![0u8; (PAGE_SIZE * PAGE_SIZE * bytes_per_pixel) as usize];
+    // shaped glpyhs are kept by id next to the chars
+    let glpyhs = "hermith".chars().map(Glpyh::Char)
+        .chain([Glpyh::Id(0), Glpyh::Id(1234), Glpyh::Id(u16::MAX)]);
+    for (idx, glpyh) in glpyhs.enumerate() {
+        // the same glpyh can be in the atlas once for each face
+        let face = Face::ALL[idx % 4];
+        let size = (10 + idx as u64, 20);
+        let pos = skyline.insert(size.0, size.1).unwrap();
+        // distinct coverage for each glpyh
+        let texel = [idx as u8 * 20, 7, 200, 255];
+        for y in pos.1..pos.1 + size.1 {
+            for x in pos.0..pos.0 + size.0 {
+                let at = ((y * PAGE_SIZE + x) * bytes_per_pixel) as usize;
+                pixels[at..at + bytes_per_pixel as usize]
+                    .copy_from_slice(&texel[..bytes_per_pixel as usize]);
+            }
+        }
+        lookup.insert((face, glpyh), AtlasEntry::new(size, pos, 0, (idx as i32 - 2, -3)));
+    }
+    // glpyhs with nothing to draw are kept too
+    lookup.insert((Face::Italic, Glpyh::Char(' ')), AtlasEntry::new((0, 0), (0, 0), 0, (0, 0)));
+    return CachedAtlas { pages: vec![pixels], skylines: vec![skyline], lookup };
+}
+
+fn round_trip(antialias: Antialias) {
+    let dir = tmp_dir(&format!("round_trip_{:?}", antialias));
+    let atlas = sample(antialias);
+    cache::save(&dir, &atlas, antialias).unwrap();
+
+    let loaded = cache::load(&dir, antialias).expect("cache should load");
+    assert_eq!(loaded.pages, atlas.pages);
+    assert_eq!(loaded.skylines[0].segments(), atlas.skylines[0].segments());
+    assert_eq!(loaded.lookup.len(), atlas.lookup.len());
+    for (key, entry) in &atlas.lookup {
+        let other = loaded.lookup[key];
+        assert_eq!((other.size, other.pos, other.page, other.bearing),
+                   (entry.size, entry.pos, entry.page, entry.bearing));
+    }
+
+    // saving again replaces the old files
+    cache::save(&dir, &atlas, antialias).unwrap();
+    assert!(cache::load(&dir, antialias).is_some());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn round_trip_grayscale() {
+    round_trip(Antialias::Grayscale);
+}
+
+#[test]
+fn round_trip_lcd() {
+    round_trip(Antialias::Lcd);
+}
+
+#[test]
+fn bad_cache_is_ignored() {
+    let dir = tmp_dir("bad_cache");
+    assert!(cache::load(&dir, Antialias::Lcd).is_none(), "missing cache");
+
+    cache::save(&dir, &sample(Antialias::Lcd), Antialias::Lcd).unwrap();
+    let lookup = dir.join("lookup");
+    let table = std::fs::read_to_string(&lookup).unwrap();
+
+    let broken = [
+        table.replacen("hermitshell atlas 5", "hermitshell atlas 0", 1),
+        format!("{}glpyh 0 65 10 10 0 0 3 0 0\n", table),
+        format!("{}glpyh 0 65 10 10 {} 0 0 0 0\n", table, PAGE_SIZE),
+        format!("{}glpyh 0 65 -10 10 0 0 0 0 0\n", table),
+        format!("{}glpyh 0 65 10 10 0 0 0 0\n", table),
+        format!("{}glpyh 4 65 10 10 0 0 0 0 0\n", table),
+        format!("{}glpyh 0 x 1 1 0 0 0 0 0\n", table),
+        format!("{}id 0 65536 1 1 0 0 0 0 0\n", table),
+        format!("{}id 0 -1 1 1 0 0 0 0 0\n", table),
+        format!("{}glpyh 0 55296 1 1 0 0 0 0 0\n", table),
+        format!("{}page 0 0 12\n", table),
+    ];
+    for text in broken {
+        std::fs::write(&lookup, text).unwrap();
+        assert!(cache::load(&dir, Antialias::Lcd).is_none());
+    }
+
+    std::fs::write(&lookup, table).unwrap();
+    std::fs::remove_file(dir.join("page_0.png")).unwrap();
+    assert!(cache::load(&dir, Antialias::Lcd).is_none(), "missing page");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn key_changes_with_font_and_settings() {
+    let fonts = |names: &[&str]| -> Vec<Vec<u8>> {
+        return names.iter().map(|name| name.as_bytes().to_vec()).collect();
+    };
+    let key = cache::atlas_dir(&fonts(&["font"]), "size 32");
+    if key.is_none() {
+        // no cache directory on this platform
+        return;
+    }
+    assert_eq!(key, cache::atlas_dir(&fonts(&["font"]), "size 32"));
+    assert_ne!(key, cache::atlas_dir(&fonts(&["font"]), "size 16"));
+    assert_ne!(key, cache::atlas_dir(&fonts(&["fonts"]), "size 32"));
+    // fallbacks and their order are part of the key
+    let chain = cache::atlas_dir(&fonts(&["font", "cjk"]), "size 32");
+    assert_ne!(key, chain);
+    assert_ne!(chain, cache::atlas_dir(&fonts(&["cjk", "font"]), "size 32"));
+    assert_ne!(chain, cache::atlas_dir(&fonts(&["fontc", "jk"]), "size 32"));
+}
+
+const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+
+#[test]
+fn lcd_atlas_round_trip() {
+    let Some((device, queue)) = common::device() else { return };
+    if std::fs::metadata(FONT).is_err() {
+        return;
+    }
+    let config = common::config(&[FONT], 37.0, Antialias::Lcd);
+    let mut atlas = FontAtlas::new(config, &device, &queue).unwrap();
+    assert_eq!(atlas.atlas.format(), wgpu::TextureFormat::Rgba8Unorm);
+    let dir = tmp_dir("lcd_atlas");
+    atlas.cache_dir = Some(dir.clone());
+
+    atlas.begin_frame();
+    let (entry, _) = atlas.glpyh(Glpyh::Char('W'), Face::Regular, &device, &queue).unwrap();
+    pollster::block_on(atlas.save_cache(&device, &queue)).unwrap();
+
+    // the rgba page read back from the gpu comes out of the cache as it went in
+    let loaded = cache::load(&dir, Antialias::Lcd).expect("cache should load");
+    assert_eq!(loaded.lookup[&(Face::Regular, Glpyh::Char('W'))].pos, entry.pos);
+    let page = &loaded.pages[entry.page as usize];
+    assert_eq!(page.len() as u64, PAGE_SIZE * PAGE_SIZE * 4);
+
+    // each subpixel has its own coverage and alpha is the most of them
+    let mut fringed = false;
+    let mut covered = false;
+    for y in entry.pos.1..entry.pos.1 + entry.size.1 {
+        for x in entry.pos.0..entry.pos.0 + entry.size.0 {
+            let at = ((y * PAGE_SIZE + x) * 4) as usize;
+            let [r, g, b, a] = [page[at], page[at + 1], page[at + 2], page[at + 3]];
+            assert_eq!(a, r.max(g).max(b));
+            fringed |= r != b;
+            covered |= a == 255;
+        }
+    }
+    assert!(fringed && covered);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use hermitshell::font_atlas::font_atlas::{Antialias, Face, FontAtlas, Glpyh, MAX_PAGES};
+use std::collections::HashMap;
+
+const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+// big enough that a page only holds a handful of glpyhs
+const FONT_SIZE: f32 = 601.0;
+
+fn atlas(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<FontAtlas> {
+    std::fs::metadata(FONT).ok()?;
+    let config = common::config(&[FONT], FONT_SIZE, Antialias::Grayscale);
+    return Some(FontAtlas::new(config, device, queue).unwrap());
+}
+
+fn pages(atlas: &FontAtlas) -> u32 {
+    return atlas.atlas.size().depth_or_array_layers;
+}
+
+fn has(atlas: &FontAtlas, c: char) -> bool {
+    return atlas.lookup.contains_key(&(Face::Regular, Glpyh::Char(c)));
+}
+
+fn page_of(atlas: &FontAtlas, c: char) -> u32 {
+    return atlas.lookup[&(Face::Regular, Glpyh::Char(c))].page;
+}
+
+const CHARS: std::ops::RangeInclusive<char> = 'A'..='z';
+
+// loads chars one frame each until every page has been added and one has
+// been evicted. hands back the chars loaded in order and the page each
+// one was on before the eviction
+fn fill(atlas: &mut FontAtlas, device: &wgpu::Device, queue: &wgpu::Queue)
+    -> (Vec<char>, HashMap<char, u32>) {
+    let mut loaded = Vec::new();
+    for c in CHARS {
+        atlas.begin_frame();
+        let generation = atlas.generation;
+        let full = pages(atlas) == MAX_PAGES;
+        let before = loaded.iter().map(|&c| (c, page_of(atlas, c))).collect();
+        assert!(atlas.glpyh(Glpyh::Char(c), Face::Regular, device, queue).is_some());
+        loaded.push(c);
+        if full && atlas.generation != generation {
+            return (loaded, before);
+        }
+    }
+    panic!("the atlas never filled up");
+}
+
+#[test]
+fn allocate_then_grow_to_max_pages() {
+    let Some((device, queue)) = common::device() else { return };
+    let Some(mut atlas) = atlas(&device, &queue) else { return };
+    assert_eq!(pages(&atlas), 1);
+    atlas.begin_frame();
+    atlas.glpyh(Glpyh::Char('A'), Face::Regular, &device, &queue).unwrap();
+    assert_eq!(page_of(&atlas, 'A'), 0);
+
+    let (loaded, before) = fill(&mut atlas, &device, &queue);
+    assert_eq!(pages(&atlas), MAX_PAGES);
+    // pages were added one at a time as the ones before filled up, small
+    // glpyhs can still go into gaps on earlier pages
+    let mut newest_page = 0;
+    for c in &loaded[..loaded.len() - 1] {
+        assert!(before[c] <= newest_page + 1, "{:?}", c);
+        newest_page = newest_page.max(before[c]);
+    }
+    assert_eq!(newest_page, MAX_PAGES - 1);
+}
+
+#[test]
+fn least_recently_used_page_is_evicted() {
+    let Some((device, queue)) = common::device() else { return };
+    let Some(mut atlas) = atlas(&device, &queue) else { return };
+    let (loaded, before) = fill(&mut atlas, &device, &queue);
+    // the first page had the oldest glpyhs so it was emptied whole and the
+    // glpyh that didn't fit went there
+    let newest = *loaded.last().unwrap();
+    assert_eq!(page_of(&atlas, newest), 0);
+    for c in &loaded[..loaded.len() - 1] {
+        if before[c] == 0 {
+            assert!(!has(&atlas, *c), "{:?}", c);
+        } else {
+            assert_eq!(page_of(&atlas, *c), before[c], "{:?}", c);
+        }
+    }
+    assert_eq!(pages(&atlas), MAX_PAGES);
+
+    // an evicted glpyh comes back when it is asked for again
+    let evicted = loaded[0];
+    assert!(!has(&atlas, evicted));
+    atlas.begin_frame();
+    assert!(atlas.glpyh(Glpyh::Char(evicted), Face::Regular, &device, &queue).is_some());
+    assert!(has(&atlas, evicted));
+}
+
+#[test]
+fn glpyhs_drawn_this_frame_are_kept() {
+    let Some((device, queue)) = common::device() else { return };
+    let Some(mut atlas) = atlas(&device, &queue) else { return };
+    let (loaded, _) = fill(&mut atlas, &device, &queue);
+    // draw everything on page 1 then force another eviction
+    let on_page_1: Vec<char> = loaded.iter().copied()
+        .filter(|&c| has(&atlas, c) && page_of(&atlas, c) == 1).collect();
+    assert!(!on_page_1.is_empty());
+    atlas.begin_frame();
+    for &c in &on_page_1 {
+        atlas.glpyh(Glpyh::Char(c), Face::Regular, &device, &queue).unwrap();
+    }
+    let mut extra = CHARS.skip(loaded.len());
+    let generation = atlas.generation;
+    while atlas.generation == generation {
+        let c = extra.next().expect("ran out of chars");
+        atlas.glpyh(Glpyh::Char(c), Face::Regular, &device, &queue);
+    }
+    for &c in &on_page_1 {
+        assert!(has(&atlas, c), "{:?}", c);
+    }
+}
+
+// what the renderer does each frame, draw the screen again until the
+// generation stops changing
+#[test]
+fn evicted_glpyhs_come_back_in_the_same_frame() {
+    let Some((device, queue)) = common::device() else { return };
+    let Some(mut atlas) = atlas(&device, &queue) else { return };
+    let (loaded, _) = fill(&mut atlas, &device, &queue);
+    // a screen of glpyhs spread over every page plus some that aren't in
+    // the atlas, so drawing it evicts pages holding glpyhs on screen
+    let screen: Vec<char> = loaded.iter().copied().step_by(3)
+        .chain(CHARS.skip(loaded.len()).take(4)).collect();
+    atlas.begin_frame();
+    let mut passes = 0;
+    loop {
+        let generation = atlas.generation;
+        for &c in &screen {
+            atlas.glpyh(Glpyh::Char(c), Face::Regular, &device, &queue);
+        }
+        passes += 1;
+        if atlas.generation == generation {
+            break;
+        }
+        assert!(passes <= MAX_PAGES as usize + 1, "never settled");
+    }
+    for &c in &screen {
+        assert!(has(&atlas, c), "{:?} missing after {} passes", c, passes);
+    }
+}
@@ -0,0 +1,96 @@
+use hermitshell::font_atlas::box_drawing;
+
+const SIZES: [(usize, usize); 4] = [(10, 20), (9, 19), (16, 33), (2, 3)];
+
+fn column(pixels: &[u8], (width, height): (usize, usize), x: usize) -> Vec<u8> {
+    return (0..height).map(|y| pixels[y * width + x]).collect();
+}
+
+fn row(pixels: &[u8], (width, _): (usize, usize), y: usize) -> Vec<u8> {
+    return pixels[y * width..(y + 1) * width].to_vec();
+}
+
+#[test]
+fn only_box_and_block_chars() {
+    for c in ['a', ' ', '\u{24ff}', '\u{25a0}', '\u{e0af}', '\u{e0b4}'] {
+        assert!(!box_drawing::is_procedural(c));
+        assert!(box_drawing::rasterize(c, 10, 20).is_none());
+    }
+    assert!(box_drawing::is_procedural('\u{2500}'));
+    assert!(box_drawing::is_procedural('\u{259f}'));
+    assert!(box_drawing::is_procedural('\u{e0b3}'));
+}
+
+#[test]
+fn every_char_fills_the_cell() {
+    let chars = ('\u{2500}'..='\u{259f}').chain('\u{e0b0}'..='\u{e0b3}');
+    for c in chars {
+        for (width, height) in SIZES {
+            let pixels = box_drawing::rasterize(c, width, height).unwrap();
+            assert_eq!(pixels.len(), width * height, "{:?} at {}x{}", c, width, height);
+        }
+        assert!(box_drawing::rasterize(c, 0, 20).is_none());
+    }
+}
+
+#[test]
+fn lines_join_across_cells() {
+    // too narrow a cell has its lines on the edge
+    for size in SIZES.into_iter().filter(|&(width, _)| width >= 4) {
+        let (width, height) = size;
+        let draw = |c| box_drawing::rasterize(c, width, height).unwrap();
+        let horizontal = column(&draw('─'), size, 0);
+        assert!(horizontal.contains(&255));
+        for c in ['─', '┼', '┌', '└', '├', '┬', '┴', '╶'] {
+            assert_eq!(column(&draw(c), size, width - 1), horizontal, "{:?}", c);
+        }
+        for c in ['┼', '┐', '┘', '┤', '┬', '┴', '╴'] {
+            assert_eq!(column(&draw(c), size, 0), horizontal, "{:?}", c);
+        }
+
+        let vertical = row(&draw('│'), size, 0);
+        for c in ['│', '┼', '┌', '┐', '├', '┤', '┬', '╷'] {
+            assert_eq!(row(&draw(c), size, height - 1), vertical, "{:?}", c);
+        }
+        for c in ['┼', '└', '┘', '├', '┤', '┴', '╵'] {
+            assert_eq!(row(&draw(c), size, 0), vertical, "{:?}", c);
+        }
+
+        // both halves of a double line
+        let double = column(&draw('═'), size, 0);
+        for c in ['╔', '╚', '╠', '╦', '╩', '╬'] {
+            assert_eq!(column(&draw(c), size, width - 1), double, "{:?}", c);
+        }
+    }
+}
+
+#[test]
+fn double_corners_keep_the_gap() {
+    let size = (10, 20);
+    let (width, height) = size;
+    let draw = |c| box_drawing::rasterize(c, width, height).unwrap();
+    // where the gap between the two halves of a double line is
+    let gap = |line: Vec<u8>| (1..line.len() - 1)
+        .find(|&idx| line[idx] == 0 && line[idx - 1] == 255 && line[idx + 1] == 255)
+        .unwrap();
+    let (gap_x, gap_y) = (gap(row(&draw('║'), size, 0)), gap(column(&draw('═'), size, 0)));
+    assert_eq!(draw('╬')[gap_y * width + gap_x], 0);
+    assert_eq!(draw('╔')[gap_y * width + gap_x], 0);
+    assert_eq!(row(&draw('╔'), size, height - 1), row(&draw('║'), size, 0));
+}
+
+#[test]
+fn blocks() {
+    for (width, height) in SIZES {
+        let draw = |c| box_drawing::rasterize(c, width, height).unwrap();
+        assert!(draw('█').iter().all(|&coverage| coverage == 255));
+        // the halves make a whole with no overlap
+        for (first, second) in [('▀', '▄'), ('▌', '▐'), ('▘', '▟'), ('▚', '▞')] {
+            for (a, b) in draw(first).iter().zip(draw(second)) {
+                assert_eq!(*a as u32 + b as u32, 255, "{:?} {:?}", first, second);
+            }
+        }
+        let shade = draw('▒');
+        assert!(shade.iter().all(|&coverage| coverage == shade[0]));
+    }
+}
@@ -0,0 +1,50 @@
+use hermitshell::font_atlas::font_atlas::CellMetrics;
+
+const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+
+fn font() -> Option<fontdue::Font> {
+    let data = std::fs::read(FONT).ok()?;
+    return fontdue::Font::from_bytes(data, fontdue::FontSettings::default()).ok();
+}
+
+#[test]
+fn cell_from_line_metrics() {
+    let Some(font) = font() else { return };
+    let cell = CellMetrics::new(&font, 32.0, 1.0, 0.0);
+    let line_metrics = font.horizontal_line_metrics(32.0).unwrap();
+    assert_eq!(cell.width, font.metrics('M', 32.0).advance_width.ceil());
+    assert_eq!(cell.height, line_metrics.new_line_size.ceil());
+    assert_eq!((cell.ascent, cell.descent), (line_metrics.ascent, line_metrics.descent));
+    // whole pixels with room for the ascent and descent
+    assert_eq!(cell.origin.0, 0.0);
+    assert_eq!(cell.origin.1, cell.origin.1.round());
+    assert!(cell.origin.1 >= cell.ascent.floor());
+    assert!(cell.origin.1 - cell.descent <= cell.height + 1.0);
+}
+
+#[test]
+fn line_height_splits_the_space() {
+    let Some(font) = font() else { return };
+    let cell = CellMetrics::new(&font, 32.0, 1.0, 0.0);
+    let tall = CellMetrics::new(&font, 32.0, 1.5, 0.0);
+    assert_eq!(tall.width, cell.width);
+    assert!(tall.height > cell.height);
+    let above = tall.origin.1 - tall.ascent;
+    let below = tall.height - tall.origin.1 + tall.descent;
+    assert!((above - below).abs() <= 1.0);
+}
+
+#[test]
+fn letter_spacing_splits_the_space() {
+    let Some(font) = font() else { return };
+    let cell = CellMetrics::new(&font, 32.0, 1.0, 0.0);
+    let wide = CellMetrics::new(&font, 32.0, 1.0, 4.0);
+    assert_eq!(wide.width, cell.width + 4.0);
+    assert_eq!(wide.origin, (2.0, cell.origin.1));
+    let narrow = CellMetrics::new(&font, 32.0, 1.0, -4.0);
+    assert_eq!(narrow.width, cell.width - 4.0);
+    assert_eq!(narrow.origin.0, -2.0);
+    // never squeezed away
+    assert_eq!(CellMetrics::new(&font, 32.0, 0.0, -1000.0).width, 1.0);
+    assert_eq!(CellMetrics::new(&font, 32.0, 0.0, -1000.0).height, 1.0);
+}
@@ -0,0 +1,21 @@
+/*  helpers shared by the tests that need a gpu
+ */
+
+use hermitshell::font_atlas::font_atlas::{Antialias, TermConfig};
+
+// None when there is no adapter, the test should be skipped
+pub fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(Default::default());
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+    return pollster::block_on(adapter.request_device(&Default::default(), None)).ok();
+}
+
+// the default config with the given font chain
+pub fn config(fonts: &[&str], font_size: f32, antialias: Antialias) -> TermConfig {
+    return TermConfig {
+        font_dirs: fonts.iter().map(|font| font.to_string()).collect(),
+        bold_font: None, italic_font: None, bold_italic_font: None,
+        font_size, scrollback_lines: 0, antialias,
+        gamma: 1.0, contrast: 0.0, line_height: 1.0, letter_spacing: 0.0,
+    };
+}
@@ -0,0 +1,50 @@
+use hermitshell::terminal::term::{CursorShape, CursorStyle, Term};
+
+fn style(shape: CursorShape, blinking: bool) -> CursorStyle {
+    return CursorStyle { shape, blinking };
+}
+
+#[test]
+fn decscusr_shapes() {
+    let mut term = Term::new(2, 8, 0);
+    assert_eq!(term.cursor_style, style(CursorShape::Block, true));
+    let shapes = [(1, CursorShape::Block, true), (2, CursorShape::Block, false),
+                  (3, CursorShape::Underline, true), (4, CursorShape::Underline, false),
+                  (5, CursorShape::Bar, true), (6, CursorShape::Bar, false)];
+    for (param, shape, blinking) in shapes {
+        term.advance(&format!("\x1b[{} q", param));
+        assert_eq!(term.cursor_style, style(shape, blinking), "{}", param);
+    }
+    // no param is the default
+    term.advance("\x1b[ q");
+    assert_eq!(term.cursor_style, CursorStyle::default());
+    // unknown shapes are ignored
+    term.advance("\x1b[6 q\x1b[9 q");
+    assert_eq!(term.cursor_style, style(CursorShape::Bar, false));
+    // without the space it isn't DECSCUSR
+    term.advance("\x1b[3q");
+    assert_eq!(term.cursor_style, style(CursorShape::Bar, false));
+}
+
+#[test]
+fn blinking_mode() {
+    let mut term = Term::new(2, 8, 0);
+    term.advance("\x1b[4 q\x1b[?12h");
+    assert_eq!(term.cursor_style, style(CursorShape::Underline, true));
+    term.advance("\x1b[?12l");
+    assert_eq!(term.cursor_style, style(CursorShape::Underline, false));
+}
+
+#[test]
+fn dectcem_hides_the_cursor() {
+    let mut term = Term::new(2, 8, 0);
+    assert!(term.cursor_visible);
+    term.advance("\x1b[?25l");
+    assert!(!term.cursor_visible);
+    term.advance("\x1b[?25h");
+    assert!(term.cursor_visible);
+    // RIS brings it back with the default shape
+    term.advance("\x1b[?25l\x1b[5 q\x1bc");
+    assert!(term.cursor_visible);
+    assert_eq!(term.cursor_style, CursorStyle::default());
+}
@@ -0,0 +1,69 @@
+use hermitshell::terminal::term::Term;
+
+// the rows in the damage
+fn damaged(term: &mut Term) -> Vec<usize> {
+    return term.take_damage().iter().enumerate()
+        .filter(|(_, &dirty)| dirty).map(|(row, _)| row).collect();
+}
+
+fn settled(rows: usize, cols: usize) -> Term {
+    let mut term = Term::new(rows, cols, 100);
+    // everything starts damaged so the first frame draws it all
+    assert_eq!(damaged(&mut term), (0..rows).collect::<Vec<_>>());
+    assert!(damaged(&mut term).is_empty());
+    return term;
+}
+
+#[test]
+fn writes_damage_their_rows() {
+    let mut term = settled(5, 10);
+    term.advance("\x1b[2;1Hab\x1b[4;3Hc");
+    assert_eq!(damaged(&mut term), [1, 3]);
+    assert!(damaged(&mut term).is_empty());
+    term.advance("\x1b[3;1H\x1b[K");
+    assert_eq!(damaged(&mut term), [2]);
+    // moving the cursor alone changes nothing
+    term.advance("\x1b[5;5H\x1b[A");
+    assert!(damaged(&mut term).is_empty());
+}
+
+#[test]
+fn scrolling_damages_the_region() {
+    let mut term = settled(5, 10);
+    term.advance("\x1b[2;4r\x1b[4;1H\n");
+    assert_eq!(damaged(&mut term), [1, 2, 3]);
+    term.advance("\x1b[r\x1b[5;1H\n");
+    assert_eq!(damaged(&mut term), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn resize_damages_everything() {
+    let mut term = settled(3, 10);
+    term.resize(4, 8);
+    assert_eq!(damaged(&mut term), [0, 1, 2, 3]);
+    assert!(damaged(&mut term).is_empty());
+}
+
+#[test]
+fn moving_the_view_damages_everything() {
+    let mut term = settled(2, 4);
+    term.advance("a\r\nb\r\nc");
+    damaged(&mut term);
+    term.scroll_display(1);
+    assert_eq!(damaged(&mut term), [0, 1]);
+    // while scrolled back a write moves every line of the view
+    term.advance("x");
+    assert_eq!(damaged(&mut term), [0, 1]);
+    term.reset_display();
+    assert_eq!(damaged(&mut term), [0, 1]);
+    // scrolling against the end of history moves nothing
+    term.scroll_display(-1);
+    assert!(damaged(&mut term).is_empty());
+}
+
+#[test]
+fn switching_screens_damages_everything() {
+    let mut term = settled(3, 4);
+    term.advance("\x1b[?1049h");
+    assert_eq!(damaged(&mut term), [0, 1, 2]);
+}
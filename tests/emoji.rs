@@ -0,0 +1,48 @@
+use hermitshell::font_atlas::emoji;
+
+const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+// wherever distros put a colour emoji font
+const EMOJI_FONTS: [&str; 3] = [
+    "/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf",
+    "/usr/share/fonts/noto/NotoColorEmoji.ttf",
+    "/usr/share/fonts/google-noto-emoji/NotoColorEmoji.ttf",
+];
+
+fn emoji_font() -> Option<Vec<u8>> {
+    return EMOJI_FONTS.iter().find_map(|path| std::fs::read(path).ok());
+}
+
+#[test]
+fn outline_fonts_have_no_colour() {
+    let Ok(data) = std::fs::read(FONT) else { return };
+    assert!(emoji::ColorFont::parse(&data).is_none());
+}
+
+#[test]
+fn not_a_font() {
+    assert!(emoji::ColorFont::parse(b"not a font").is_none());
+}
+
+#[test]
+fn emoji_in_colour() {
+    let Some(data) = emoji_font() else { return };
+    let font = emoji::ColorFont::parse(&data).unwrap();
+    let (metrics, pixels) = emoji::rasterize(&font, '\u{1f600}', 32.0, 64.0).unwrap();
+    assert_eq!(emoji::metrics(&font, '\u{1f600}', 32.0, 64.0), Some(metrics));
+    // chars without a colour glpyh are left to fontdue
+    assert!(emoji::metrics(&font, 'a', 32.0, 64.0).is_none());
+    assert!(metrics.width > 0 && metrics.height > 0);
+    assert_eq!(pixels.len(), metrics.width * metrics.height * 4);
+    // a smiley isn't grey
+    assert!(pixels.chunks(4).any(|pixel| pixel[3] > 0
+        && (pixel[0] != pixel[1] || pixel[1] != pixel[2])));
+}
+
+#[test]
+fn emoji_fit_two_cells() {
+    let Some(data) = emoji_font() else { return };
+    let font = emoji::ColorFont::parse(&data).unwrap();
+    let metrics = emoji::metrics(&font, '\u{1f600}', 32.0, 20.0).unwrap();
+    assert!(metrics.width <= 20);
+    assert!(metrics.advance_width <= 20.0);
+}
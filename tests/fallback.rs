@@ -0,0 +1,110 @@
+mod common;
+
+use hermitshell::font_atlas::font_atlas::{Antialias, Face, FontAtlas, Glpyh, TermConfig};
+
+const MONO: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+const SANS: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+const FONT_SIZE: f32 = 24.0;
+// in dejavu sans but not sans mono
+const MISSING: char = '\u{1c4}';
+// in no font at all
+const NOWHERE: char = '\u{10ffff}';
+
+fn config(fonts: &[&str]) -> TermConfig {
+    return common::config(fonts, FONT_SIZE, Antialias::Grayscale);
+}
+
+fn atlas(fonts: &[&str], device: &wgpu::Device, queue: &wgpu::Queue) -> FontAtlas {
+    return FontAtlas::new(config(fonts), device, queue).unwrap();
+}
+
+fn fonts_exist() -> bool {
+    return std::fs::metadata(MONO).is_ok() && std::fs::metadata(SANS).is_ok();
+}
+
+fn size(metrics: fontdue::Metrics) -> (usize, usize) {
+    return (metrics.width, metrics.height);
+}
+
+#[test]
+fn missing_glpyhs_come_from_the_fallback() {
+    let Some((device, queue)) = common::device() else { return };
+    if !fonts_exist() {
+        return;
+    }
+    let sans = fontdue::Font::from_bytes(std::fs::read(SANS).unwrap(),
+                                         fontdue::FontSettings::default()).unwrap();
+    assert!(sans.has_glyph(MISSING));
+    let expected = sans.metrics(MISSING, FONT_SIZE);
+
+    // fallbacks that don't load are skipped
+    let mut atlas = atlas(&[MONO, "/nonexistent/font.ttf", SANS], &device, &queue);
+    let metrics = atlas.metrics(Glpyh::Char(MISSING), Face::Regular);
+    assert_eq!(size(metrics), size(expected));
+    assert_eq!((metrics.xmin, metrics.ymin), (expected.xmin, expected.ymin));
+    atlas.begin_frame();
+    let (entry, _) = atlas.glpyh(Glpyh::Char(MISSING), Face::Regular, &device, &queue)
+        .unwrap();
+    assert_eq!(entry.size, (expected.width as u64, expected.height as u64));
+}
+
+#[test]
+fn glpyhs_no_font_has_are_tofu() {
+    let Some((device, queue)) = common::device() else { return };
+    if !fonts_exist() {
+        return;
+    }
+    let mut chain = atlas(&[MONO, SANS], &device, &queue);
+    let mut atlas = atlas(&[MONO], &device, &queue);
+    let tofu = atlas.metrics(Glpyh::Char(NOWHERE), Face::Regular);
+    assert!(tofu.width > 0 && tofu.height > 0);
+    assert_eq!(tofu.ymin, 0);
+    // without the fallback the same char is the tofu too
+    assert_eq!(size(atlas.metrics(Glpyh::Char(MISSING), Face::Regular)), size(tofu));
+    atlas.begin_frame();
+    let (entry, _) = atlas.glpyh(Glpyh::Char(NOWHERE), Face::Regular, &device, &queue)
+        .unwrap();
+    assert_eq!(entry.size, (tofu.width as u64, tofu.height as u64));
+
+    // and a fallback doesn't stop it being used for chars nobody has
+    assert_eq!(size(chain.metrics(Glpyh::Char(NOWHERE), Face::Regular)), size(tofu));
+    assert_ne!(size(chain.metrics(Glpyh::Char(MISSING), Face::Regular)), size(tofu));
+}
+
+#[test]
+fn cluster_metrics_match_the_drawn_cluster() {
+    let Some((device, queue)) = common::device() else { return };
+    if !fonts_exist() {
+        return;
+    }
+    let mut atlas = atlas(&[MONO], &device, &queue);
+    // no precomposed x with an acute so it stays a cluster
+    let cluster = atlas.cluster("x\u{301}");
+    assert!(matches!(cluster, Glpyh::Cluster(_)));
+    let metrics = atlas.metrics(cluster, Face::Regular);
+    assert!(metrics.width > 0 && metrics.height > 0);
+    atlas.begin_frame();
+    let (entry, _) = atlas.glpyh(cluster, Face::Regular, &device, &queue).unwrap();
+    assert_eq!(entry.size, (metrics.width as u64, metrics.height as u64));
+    assert_eq!(entry.bearing, (metrics.xmin, metrics.ymin));
+    assert_eq!(size(atlas.metrics(cluster, Face::Regular)), size(metrics));
+    // each face is measured on its own
+    let bold = atlas.metrics(cluster, Face::Bold);
+    assert!(bold.width >= metrics.width);
+}
+
+#[test]
+fn a_primary_font_that_wont_load_is_an_error() {
+    let Some((device, queue)) = common::device() else { return };
+    let corrupt = std::env::temp_dir()
+        .join(format!("hermitshell_corrupt_{}.ttf", std::process::id()));
+    std::fs::write(&corrupt, b"not a font").unwrap();
+    let corrupt = corrupt.to_str().unwrap().to_string();
+    for fonts in [vec!["/nonexistent/font.ttf"], vec![corrupt.as_str(), MONO], vec![]] {
+        let error = FontAtlas::new(config(&fonts), &device, &queue).err();
+        assert!(error.is_some(), "{:?}", fonts);
+    }
+    assert!(FontAtlas::new(config(&["/nonexistent/font.ttf"]), &device, &queue).err()
+        .unwrap().contains("/nonexistent/font.ttf"));
+    let _ = std::fs::remove_file(&corrupt);
+}
@@ -0,0 +1,134 @@
+use hermitshell::terminal::keys::{encode, modifier_param, KeyModes};
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey, SmolStr};
+
+fn named(key: NamedKey, mods: ModifiersState, modes: KeyModes) -> Vec<u8> {
+    return encode(&Key::Named(key), KeyLocation::Standard, None, mods, modes)
+        .unwrap();
+}
+
+fn chars(text: &str, location: KeyLocation, mods: ModifiersState,
+         modes: KeyModes) -> Vec<u8> {
+    return encode(&Key::Character(SmolStr::new(text)), location, Some(text),
+                  mods, modes).unwrap();
+}
+
+const NONE: ModifiersState = ModifiersState::empty();
+const APP_CURSOR: KeyModes = KeyModes { app_cursor: true, app_keypad: false };
+const APP_KEYPAD: KeyModes = KeyModes { app_cursor: false, app_keypad: true };
+
+#[test]
+fn modifier_params() {
+    assert_eq!(modifier_param(NONE), 1);
+    assert_eq!(modifier_param(ModifiersState::SHIFT), 2);
+    assert_eq!(modifier_param(ModifiersState::ALT), 3);
+    assert_eq!(modifier_param(ModifiersState::SHIFT | ModifiersState::ALT), 4);
+    assert_eq!(modifier_param(ModifiersState::CONTROL), 5);
+    assert_eq!(modifier_param(ModifiersState::CONTROL | ModifiersState::SHIFT), 6);
+    assert_eq!(modifier_param(ModifiersState::SUPER), 9);
+}
+
+#[test]
+fn cursor_keys() {
+    let modes = KeyModes::default();
+    assert_eq!(named(NamedKey::ArrowUp, NONE, modes), b"\x1b[A");
+    assert_eq!(named(NamedKey::ArrowDown, NONE, modes), b"\x1b[B");
+    assert_eq!(named(NamedKey::ArrowRight, NONE, modes), b"\x1b[C");
+    assert_eq!(named(NamedKey::ArrowLeft, NONE, modes), b"\x1b[D");
+    assert_eq!(named(NamedKey::Home, NONE, modes), b"\x1b[H");
+    assert_eq!(named(NamedKey::End, NONE, modes), b"\x1b[F");
+}
+
+#[test]
+fn application_cursor_keys() {
+    assert_eq!(named(NamedKey::ArrowUp, NONE, APP_CURSOR), b"\x1bOA");
+    assert_eq!(named(NamedKey::ArrowLeft, NONE, APP_CURSOR), b"\x1bOD");
+    assert_eq!(named(NamedKey::Home, NONE, APP_CURSOR), b"\x1bOH");
+    // modifiers always use the CSI form
+    assert_eq!(named(NamedKey::ArrowUp, ModifiersState::CONTROL, APP_CURSOR),
+               b"\x1b[1;5A");
+}
+
+#[test]
+fn modified_cursor_keys() {
+    let modes = KeyModes::default();
+    assert_eq!(named(NamedKey::ArrowUp, ModifiersState::CONTROL, modes),
+               b"\x1b[1;5A");
+    assert_eq!(named(NamedKey::ArrowRight, ModifiersState::SHIFT, modes),
+               b"\x1b[1;2C");
+    assert_eq!(named(NamedKey::ArrowLeft, ModifiersState::ALT, modes),
+               b"\x1b[1;3D");
+    assert_eq!(named(NamedKey::End,
+                     ModifiersState::CONTROL | ModifiersState::SHIFT, modes),
+               b"\x1b[1;6F");
+}
+
+#[test]
+fn function_keys() {
+    let modes = KeyModes::default();
+    assert_eq!(named(NamedKey::F1, NONE, modes), b"\x1bOP");
+    assert_eq!(named(NamedKey::F4, NONE, modes), b"\x1bOS");
+    assert_eq!(named(NamedKey::F1, ModifiersState::CONTROL, modes), b"\x1b[1;5P");
+    assert_eq!(named(NamedKey::F5, NONE, modes), b"\x1b[15~");
+    assert_eq!(named(NamedKey::F6, NONE, modes), b"\x1b[17~");
+    assert_eq!(named(NamedKey::F10, NONE, modes), b"\x1b[21~");
+    assert_eq!(named(NamedKey::F11, NONE, modes), b"\x1b[23~");
+    assert_eq!(named(NamedKey::F12, ModifiersState::SHIFT, modes), b"\x1b[24;2~");
+}
+
+#[test]
+fn editing_keys() {
+    let modes = KeyModes::default();
+    assert_eq!(named(NamedKey::Insert, NONE, modes), b"\x1b[2~");
+    assert_eq!(named(NamedKey::Delete, NONE, modes), b"\x1b[3~");
+    assert_eq!(named(NamedKey::PageUp, NONE, modes), b"\x1b[5~");
+    assert_eq!(named(NamedKey::PageDown, ModifiersState::CONTROL, modes),
+               b"\x1b[6;5~");
+    assert_eq!(named(NamedKey::Backspace, NONE, modes), b"\x7f");
+    assert_eq!(named(NamedKey::Backspace, ModifiersState::CONTROL, modes), b"\x08");
+    assert_eq!(named(NamedKey::Enter, NONE, modes), b"\r");
+    assert_eq!(named(NamedKey::Tab, NONE, modes), b"\t");
+    assert_eq!(named(NamedKey::Tab, ModifiersState::SHIFT, modes), b"\x1b[Z");
+    assert_eq!(named(NamedKey::Escape, NONE, modes), b"\x1b");
+}
+
+#[test]
+fn control_chars() {
+    let modes = KeyModes::default();
+    let ctrl = ModifiersState::CONTROL;
+    assert_eq!(chars("c", KeyLocation::Standard, ctrl, modes), b"\x03");
+    assert_eq!(chars("a", KeyLocation::Standard, ctrl, modes), b"\x01");
+    assert_eq!(chars("z", KeyLocation::Standard, ctrl, modes), b"\x1a");
+    assert_eq!(chars("[", KeyLocation::Standard, ctrl, modes), b"\x1b");
+    assert_eq!(chars("@", KeyLocation::Standard, ctrl, modes), b"\x00");
+    assert_eq!(named(NamedKey::Space, ctrl, modes), b"\x00");
+}
+
+#[test]
+fn alt_prefixes_escape() {
+    let modes = KeyModes::default();
+    let alt = ModifiersState::ALT;
+    assert_eq!(chars("x", KeyLocation::Standard, alt, modes), b"\x1bx");
+    assert_eq!(chars("c", KeyLocation::Standard,
+                     alt | ModifiersState::CONTROL, modes), b"\x1b\x03");
+    assert_eq!(named(NamedKey::Backspace, alt, modes), b"\x1b\x7f");
+}
+
+#[test]
+fn keypad() {
+    // normal keypad sends the digits
+    assert_eq!(chars("5", KeyLocation::Numpad, NONE, KeyModes::default()), b"5");
+    assert_eq!(chars("5", KeyLocation::Numpad, NONE, APP_KEYPAD), b"\x1bOu");
+    assert_eq!(chars("0", KeyLocation::Numpad, NONE, APP_KEYPAD), b"\x1bOp");
+    assert_eq!(chars("+", KeyLocation::Numpad, NONE, APP_KEYPAD), b"\x1bOk");
+    // the main keyboard is not affected
+    assert_eq!(chars("5", KeyLocation::Standard, NONE, APP_KEYPAD), b"5");
+    assert_eq!(encode(&Key::Named(NamedKey::Enter), KeyLocation::Numpad, None,
+                      NONE, APP_KEYPAD).unwrap(), b"\x1bOM");
+}
+
+#[test]
+fn text() {
+    let modes = KeyModes::default();
+    assert_eq!(chars("é", KeyLocation::Standard, NONE, modes), "é".as_bytes());
+    assert_eq!(chars("A", KeyLocation::Standard, ModifiersState::SHIFT, modes), b"A");
+}
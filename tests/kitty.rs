@@ -0,0 +1,114 @@
+use hermitshell::terminal::keys::KeyModes;
+use hermitshell::terminal::kitty::*;
+use hermitshell::terminal::term::Term;
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey, SmolStr};
+
+fn kitty(key: Key, unmodified: Key, text: Option<&str>, mods: ModifiersState,
+         event: KeyEventType, flags: u8) -> Option<Vec<u8>> {
+    return encode(&key, &unmodified, KeyLocation::Standard, text, mods, event,
+                  flags, KeyModes::default());
+}
+
+fn chr(text: &str) -> Key {
+    return Key::Character(SmolStr::new(text));
+}
+
+const NONE: ModifiersState = ModifiersState::empty();
+
+#[test]
+fn disambiguate() {
+    let press = KeyEventType::Press;
+    // ctrl+i and tab are told apart
+    assert_eq!(kitty(chr("i"), chr("i"), None, ModifiersState::CONTROL, press,
+                     DISAMBIGUATE).unwrap(), b"\x1b[105;5u");
+    assert_eq!(kitty(Key::Named(NamedKey::Tab), Key::Named(NamedKey::Tab),
+                     Some("\t"), NONE, press, DISAMBIGUATE).unwrap(), b"\t");
+    assert_eq!(kitty(Key::Named(NamedKey::Escape), Key::Named(NamedKey::Escape),
+                     None, NONE, press, DISAMBIGUATE).unwrap(), b"\x1b[27u");
+    // plain and shifted text stays text
+    assert_eq!(kitty(chr("a"), chr("a"), Some("a"), NONE, press,
+                     DISAMBIGUATE).unwrap(), b"a");
+    assert_eq!(kitty(chr("A"), chr("a"), Some("A"), ModifiersState::SHIFT, press,
+                     DISAMBIGUATE).unwrap(), b"A");
+    assert_eq!(kitty(chr("a"), chr("a"), Some("a"), ModifiersState::ALT, press,
+                     DISAMBIGUATE).unwrap(), b"\x1b[97;3u");
+    assert_eq!(kitty(Key::Named(NamedKey::Enter), Key::Named(NamedKey::Enter),
+                     Some("\r"), ModifiersState::CONTROL, press,
+                     DISAMBIGUATE).unwrap(), b"\x1b[13;5u");
+}
+
+#[test]
+fn functional_keys() {
+    let press = KeyEventType::Press;
+    let up = Key::Named(NamedKey::ArrowUp);
+    assert_eq!(kitty(up.clone(), up.clone(), None, NONE, press,
+                     DISAMBIGUATE).unwrap(), b"\x1b[A");
+    assert_eq!(kitty(up.clone(), up, None, ModifiersState::CONTROL, press,
+                     DISAMBIGUATE).unwrap(), b"\x1b[1;5A");
+    let f3 = Key::Named(NamedKey::F3);
+    assert_eq!(kitty(f3.clone(), f3, None, NONE, press, DISAMBIGUATE).unwrap(),
+               b"\x1b[13~");
+}
+
+#[test]
+fn event_types() {
+    let flags = DISAMBIGUATE | REPORT_EVENTS;
+    // releases are dropped unless asked for
+    assert_eq!(kitty(chr("a"), chr("a"), None, NONE, KeyEventType::Release,
+                     DISAMBIGUATE), None);
+    assert_eq!(kitty(chr("a"), chr("a"), None, NONE, KeyEventType::Release,
+                     flags).unwrap(), b"\x1b[97;1:3u");
+    assert_eq!(kitty(chr("a"), chr("a"), None, ModifiersState::CONTROL,
+                     KeyEventType::Repeat, flags).unwrap(), b"\x1b[97;5:2u");
+    let up = Key::Named(NamedKey::ArrowUp);
+    assert_eq!(kitty(up.clone(), up, None, NONE, KeyEventType::Release,
+                     flags).unwrap(), b"\x1b[1;1:3A");
+    // enter release is only sent with all keys reported
+    let enter = Key::Named(NamedKey::Enter);
+    assert_eq!(kitty(enter.clone(), enter, None, NONE, KeyEventType::Release,
+                     flags), None);
+}
+
+#[test]
+fn report_all_keys() {
+    let press = KeyEventType::Press;
+    let flags = DISAMBIGUATE | REPORT_ALL_KEYS;
+    assert_eq!(kitty(chr("a"), chr("a"), Some("a"), NONE, press, flags).unwrap(),
+               b"\x1b[97u");
+    assert_eq!(kitty(Key::Named(NamedKey::Enter), Key::Named(NamedKey::Enter),
+                     Some("\r"), NONE, press, flags).unwrap(), b"\x1b[13u");
+    assert_eq!(kitty(Key::Named(NamedKey::Shift), Key::Named(NamedKey::Shift),
+                     None, ModifiersState::SHIFT, press, flags).unwrap(),
+               b"\x1b[57441;2u");
+    // alternate and text fields
+    let flags = flags | REPORT_ALTERNATES | REPORT_TEXT;
+    assert_eq!(kitty(chr("A"), chr("a"), Some("A"), ModifiersState::SHIFT, press,
+                     flags).unwrap(), b"\x1b[97:65;2;65u");
+}
+
+#[test]
+fn flag_stack() {
+    let mut term = Term::new(24, 80, 0);
+    term.advance("\x1b[?u");
+    assert_eq!(std::mem::take(&mut term.responses), b"\x1b[?0u");
+
+    term.advance("\x1b[>1u\x1b[>3u");
+    assert_eq!(term.kitty_flags.current(), 3);
+    term.advance("\x1b[<u");
+    assert_eq!(term.kitty_flags.current(), 1);
+
+    // set, or and clear the top of the stack
+    term.advance("\x1b[=24;1u");
+    assert_eq!(term.kitty_flags.current(), 24);
+    term.advance("\x1b[=1;2u");
+    assert_eq!(term.kitty_flags.current(), 25);
+    term.advance("\x1b[=8;3u");
+    assert_eq!(term.kitty_flags.current(), 17);
+
+    term.advance("\x1b[?u");
+    assert_eq!(std::mem::take(&mut term.responses), b"\x1b[?17u");
+
+    // popping more than the stack holds resets it
+    term.advance("\x1b[<5u");
+    assert_eq!(term.kitty_flags.current(), 0);
+}
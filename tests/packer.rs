@@ -0,0 +1,86 @@
+use hermitshell::font_atlas::packer::{Point, Skyline};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PAGE: u64 = 256;
+
+fn overlaps((a_size, a_pos): &(Point, Point), (b_size, b_pos): &(Point, Point)) -> bool {
+    return a_pos.0 < b_pos.0 + b_size.0 && b_pos.0 < a_pos.0 + a_size.0
+        && a_pos.1 < b_pos.1 + b_size.1 && b_pos.1 < a_pos.1 + a_size.1;
+}
+
+// inserts boxes until one doesn't fit, checking each lands inside the page
+// and apart from the others. hands back the boxes placed
+fn fill(skyline: &mut Skyline, sizes: impl Iterator<Item = Point>) -> Vec<(Point, Point)> {
+    let mut placed: Vec<(Point, Point)> = Vec::new();
+    for size in sizes {
+        let Some(pos) = skyline.insert(size.0, size.1) else { break };
+        assert!(pos.0 + size.0 <= PAGE && pos.1 + size.1 <= PAGE, "box out of the page");
+        for other in &placed {
+            assert!(!overlaps(&(size, pos), other), "boxes overlap");
+        }
+        placed.push((size, pos));
+    }
+    return placed;
+}
+
+#[test]
+fn random_boxes_never_overlap() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    for _ in 0..20 {
+        let max_side = rng.gen_range(1..64);
+        let mut skyline = Skyline::new(PAGE, PAGE);
+        let sizes: Vec<Point> = (0..2000)
+            .map(|_| (rng.gen_range(1..=max_side), rng.gen_range(1..=max_side)))
+            .collect();
+        let placed = fill(&mut skyline, sizes.into_iter());
+        assert!(!placed.is_empty());
+    }
+}
+
+#[test]
+fn glpyh_like_boxes_fill_the_page() {
+    // lots of similar small boxes like a font at one size
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut skyline = Skyline::new(PAGE, PAGE);
+    let sizes: Vec<Point> = (0..3000)
+        .map(|_| (rng.gen_range(8..20), rng.gen_range(10..32))).collect();
+    let placed = fill(&mut skyline, sizes.into_iter());
+    assert!(placed.len() < 3000, "page should fill up");
+
+    // doesn't waste much of the page
+    let area: u64 = placed.iter().map(|((width, height), _)| width * height).sum();
+    assert!(area * 2 >= PAGE * PAGE);
+}
+
+#[test]
+fn lowest_then_leftmost() {
+    let mut skyline = Skyline::new(PAGE, PAGE);
+    assert_eq!(skyline.insert(10, 20), Some((0, 0)));
+    assert_eq!(skyline.insert(PAGE - 10, 5), Some((10, 0)));
+    // on top of the short box is lower than on the tall one
+    assert_eq!(skyline.insert(10, 5), Some((10, 5)));
+    assert_eq!(skyline.insert(10, 5), Some((20, 5)));
+    assert_eq!(skyline.segments(), [(0, 20, 10), (10, 10, 20), (30, 5, PAGE - 30)]);
+}
+
+#[test]
+fn a_full_page_is_rejected() {
+    let mut skyline = Skyline::new(PAGE, PAGE);
+    assert_eq!(skyline.insert(PAGE + 1, 1), None);
+    assert_eq!(skyline.insert(1, PAGE + 1), None);
+    assert_eq!(skyline.insert(PAGE, PAGE), Some((0, 0)));
+    assert_eq!(skyline.insert(1, 1), None);
+
+    // a row of boxes the page height leaves no room across
+    let mut skyline = Skyline::new(PAGE, PAGE);
+    for x in 0..4 {
+        assert_eq!(skyline.insert(PAGE / 4, PAGE), Some((x * PAGE / 4, 0)));
+    }
+    assert_eq!(skyline.insert(1, 1), None);
+
+    // a cleared page is empty again
+    skyline.clear();
+    assert_eq!(skyline.segments(), [(0, 0, PAGE)]);
+    assert_eq!(skyline.insert(PAGE, PAGE), Some((0, 0)));
+}
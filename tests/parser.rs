@@ -0,0 +1,155 @@
+use hermitshell::terminal::parser::{Action, Params, Parser};
+
+fn parse(input: &str) -> Vec<Action> {
+    return Parser::new().parse(input);
+}
+
+fn csi(params: Params, intermediates: &[char], action: char) -> Action {
+    return Action::CsiDispatch { params, intermediates: intermediates.to_vec(),
+                                 ignore: false, action };
+}
+
+fn osc(parts: &[&str]) -> Action {
+    return Action::OscDispatch(parts.iter().map(|part| part.to_string()).collect());
+}
+
+#[test]
+fn print_and_execute() {
+    assert_eq!(parse("a\r\n\x7f"), vec![Action::Print('a'), Action::Execute('\r'),
+                                        Action::Execute('\n')]);
+}
+
+#[test]
+fn csi_params() {
+    assert_eq!(parse("\x1b[1;23H"), vec![csi(vec![vec![1], vec![23]], &[], 'H')]);
+    assert_eq!(parse("\x1b[m"), vec![csi(vec![], &[], 'm')]);
+    // empty params are 0
+    assert_eq!(parse("\x1b[;5H"), vec![csi(vec![vec![0], vec![5]], &[], 'H')]);
+    assert_eq!(parse("\x1b[5;H"), vec![csi(vec![vec![5], vec![0]], &[], 'H')]);
+    // values saturate rather than wrap
+    assert_eq!(parse("\x1b[99999999A"), vec![csi(vec![vec![u16::MAX]], &[], 'A')]);
+}
+
+#[test]
+fn csi_subparams() {
+    assert_eq!(parse("\x1b[38:2::10:20:30m"),
+               vec![csi(vec![vec![38, 2, 0, 10, 20, 30]], &[], 'm')]);
+    assert_eq!(parse("\x1b[4:3;38:5:200m"),
+               vec![csi(vec![vec![4, 3], vec![38, 5, 200]], &[], 'm')]);
+}
+
+#[test]
+fn private_markers_and_intermediates() {
+    assert_eq!(parse("\x1b[?1049h"), vec![csi(vec![vec![1049]], &['?'], 'h')]);
+    assert_eq!(parse("\x1b[>1u"), vec![csi(vec![vec![1]], &['>'], 'u')]);
+    assert_eq!(parse("\x1b[2 q"), vec![csi(vec![vec![2]], &[' '], 'q')]);
+    assert_eq!(parse("\x1b[!p"), vec![csi(vec![], &['!'], 'p')]);
+    // a private marker after a param makes the sequence invalid
+    assert_eq!(parse("\x1b[1?hx"), vec![Action::Print('x')]);
+    assert_eq!(parse("\x1b(B"), vec![Action::EscDispatch {
+        intermediates: vec!['('], ignore: false, action: 'B' }]);
+    assert_eq!(parse("\x1b7"), vec![Action::EscDispatch {
+        intermediates: vec![], ignore: false, action: '7' }]);
+}
+
+#[test]
+fn controls_inside_csi_execute() {
+    assert_eq!(parse("\x1b[1\n2A"), vec![Action::Execute('\n'),
+                                         csi(vec![vec![12]], &[], 'A')]);
+}
+
+#[test]
+fn osc_terminators() {
+    assert_eq!(parse("\x1b]0;title\x07"), vec![osc(&["0", "title"])]);
+    // ST as ESC \ leaves nothing behind for the term to filter out
+    assert_eq!(parse("\x1b]2;a;b\x1b\\x"), vec![osc(&["2", "a", "b"]),
+                                               Action::Print('x')]);
+    // and as C1
+    assert_eq!(parse("\u{9d}0;t\u{9c}"), vec![osc(&["0", "t"])]);
+    // an osc split across reads
+    let mut parser = Parser::new();
+    assert_eq!(parser.parse("\x1b]0;ti"), vec![]);
+    assert_eq!(parser.parse("tle\x07"), vec![osc(&["0", "title"])]);
+}
+
+#[test]
+fn dcs_hook_put_unhook() {
+    assert_eq!(parse("\x1bP1$qm\x1b\\"), vec![
+        Action::DcsHook { params: vec![vec![1]], intermediates: vec!['$'],
+                          ignore: false, action: 'q' },
+        Action::DcsPut('m'),
+        Action::DcsUnhook,
+    ]);
+    assert_eq!(parse("\u{90}q#0\u{9c}"), vec![
+        Action::DcsHook { params: vec![], intermediates: vec![],
+                          ignore: false, action: 'q' },
+        Action::DcsPut('#'),
+        Action::DcsPut('0'),
+        Action::DcsUnhook,
+    ]);
+}
+
+#[test]
+fn can_and_sub_abort() {
+    assert_eq!(parse("\x1b[12\x18A"), vec![Action::Execute('\x18'), Action::Print('A')]);
+    assert_eq!(parse("\x1b[12\x1aA"), vec![Action::Execute('\x1a'), Action::Print('A')]);
+    // a string is still dispatched, or unhooked, when it is cut off
+    assert_eq!(parse("\x1b]0;t\x18"), vec![osc(&["0", "t"]), Action::Execute('\x18')]);
+    assert_eq!(parse("\x1bPqa\x18"), vec![
+        Action::DcsHook { params: vec![], intermediates: vec![],
+                          ignore: false, action: 'q' },
+        Action::DcsPut('a'),
+        Action::DcsUnhook,
+        Action::Execute('\x18'),
+    ]);
+}
+
+#[test]
+fn c1_controls() {
+    assert_eq!(parse("\u{9b}2J"), vec![csi(vec![vec![2]], &[], 'J')]);
+    assert_eq!(parse("\u{85}"), vec![Action::Execute('\u{85}')]);
+    // C1 cuts off a sequence in progress
+    assert_eq!(parse("\x1b[1\u{84}"), vec![Action::Execute('\u{84}')]);
+    // sos, pm and apc strings are dropped
+    assert_eq!(parse("\u{9e}hidden\u{9c}x"), vec![Action::Print('x')]);
+    assert_eq!(parse("\x1b_hidden\x1b\\x"), vec![Action::Print('x')]);
+}
+
+#[test]
+fn too_many_params_are_ignored() {
+    let params = vec!["1"; 40].join(";");
+    let actions = parse(&format!("\x1b[{}m", params));
+    let [Action::CsiDispatch { params, ignore, action, .. }] = actions.as_slice()
+        else { panic!("{:?}", actions) };
+    assert!(*ignore);
+    assert_eq!(*action, 'm');
+    assert_eq!(params.len(), 32);
+    // the next sequence starts clean
+    let mut parser = Parser::new();
+    parser.parse(&format!("\x1b[{}m", vec!["1"; 40].join(";")));
+    assert_eq!(parser.parse("\x1b[1m"), vec![csi(vec![vec![1]], &[], 'm')]);
+}
+
+#[test]
+fn too_many_intermediates_are_ignored() {
+    let actions = parse("\x1b[1 !#p");
+    let [Action::CsiDispatch { ignore, .. }] = actions.as_slice()
+        else { panic!("{:?}", actions) };
+    assert!(*ignore);
+}
+
+#[test]
+fn osc_is_truncated() {
+    let long = "x".repeat(100_000);
+    let actions = parse(&format!("\x1b]2;{}\x07a", long));
+    assert_eq!(actions.len(), 2);
+    match &actions[0] {
+        Action::OscDispatch(params) => {
+            assert_eq!(params[0], "2");
+            assert!(params[1].len() < 1024);
+            assert!(params[1].chars().all(|c| c == 'x'));
+        }
+        other => panic!("expected an osc, got {:?}", other),
+    }
+    assert_eq!(actions[1], Action::Print('a'));
+}
@@ -0,0 +1,76 @@
+use hermitshell::terminal::term::Term;
+
+fn row(term: &Term, row: usize) -> String {
+    return term.grid.row(row).iter().map(|cell| cell.c).collect();
+}
+
+fn history(term: &Term) -> Vec<String> {
+    return (0..term.scrollback.len())
+        .map(|idx| term.scrollback.line(idx).iter().map(|cell| cell.c).collect())
+        .collect();
+}
+
+#[test]
+fn shrinking_pushes_rows_into_scrollback() {
+    let mut term = Term::new(4, 3, 100);
+    term.advance("aaa\r\nbbb\r\nccc\r\nddd");
+    term.resize(2, 3);
+    // rows leave the top so the cursor's row stays on screen
+    assert_eq!(history(&term), ["aaa", "bbb"]);
+    assert_eq!((row(&term, 0), row(&term, 1)), ("ccc".into(), "ddd".into()));
+    assert_eq!(term.grid.cursor.row, 1);
+}
+
+#[test]
+fn shrinking_below_the_cursor_drops_nothing() {
+    let mut term = Term::new(4, 3, 100);
+    term.advance("aaa\r\nbbb");
+    term.resize(2, 3);
+    assert!(history(&term).is_empty());
+    assert_eq!((row(&term, 0), row(&term, 1)), ("aaa".into(), "bbb".into()));
+}
+
+#[test]
+fn cursor_is_clamped() {
+    let mut term = Term::new(5, 10, 100);
+    term.advance("\x1b[3;9H");
+    term.resize(5, 4);
+    assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (2, 3));
+    term.resize(1, 4);
+    assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (0, 3));
+}
+
+#[test]
+fn growing_pads_with_blanks() {
+    let mut term = Term::new(2, 2, 100);
+    term.advance("ab\r\ncd");
+    term.resize(3, 4);
+    assert_eq!((term.grid.rows, term.grid.cols), (3, 4));
+    assert_eq!(row(&term, 0), "ab  ");
+    assert_eq!(row(&term, 1), "cd  ");
+    assert_eq!(row(&term, 2), "    ");
+    // the new space is usable
+    term.advance("\x1b[3;4Hz");
+    assert_eq!(row(&term, 2), "   z");
+}
+
+#[test]
+fn alt_screen_rows_stay_out_of_scrollback() {
+    let mut term = Term::new(3, 2, 100);
+    term.advance("pp\r\nqq\r\nrr\x1b[?1049hxx\r\nyy\r\nzz");
+    term.resize(1, 2);
+    // only the primary screen's rows are kept
+    assert_eq!(history(&term), ["pp", "qq"]);
+}
+
+#[test]
+fn zero_size_is_one_cell() {
+    let mut term = Term::new(0, 0, 10);
+    assert_eq!((term.grid.rows, term.grid.cols), (1, 1));
+    term.advance("a\r\n\x1b[2;2r\tb");
+    term.resize(0, 0);
+    assert_eq!((term.grid.rows, term.grid.cols), (1, 1));
+    term.advance("c\n");
+    term.resize(3, 0);
+    assert_eq!((term.grid.rows, term.grid.cols), (3, 1));
+}
@@ -0,0 +1,97 @@
+use hermitshell::terminal::grid::Cell;
+use hermitshell::terminal::scrollback::Scrollback;
+use hermitshell::terminal::term::Term;
+
+fn line(c: char) -> Vec<Cell> {
+    return vec![Cell { c, ..Default::default() }; 2];
+}
+
+fn text(cells: &[Cell]) -> String {
+    return cells.iter().map(|cell| cell.c).collect();
+}
+
+fn view(term: &Term) -> Vec<String> {
+    return (0..term.grid.rows).map(|row| text(term.display_row(row))).collect();
+}
+
+// a 2 row term that has printed lines a, b, c, ... one per row
+fn printed(lines: usize, limit: usize) -> Term {
+    let mut term = Term::new(2, 2, limit);
+    let text: Vec<String> = (0..lines)
+        .map(|idx| ((b'a' + idx as u8) as char).to_string()).collect();
+    term.advance(&text.join("\r\n"));
+    return term;
+}
+
+#[test]
+fn ring_drops_the_oldest_lines() {
+    let mut scrollback = Scrollback::new(3);
+    for c in ['a', 'b', 'c', 'd', 'e'] {
+        scrollback.push(line(c));
+    }
+    assert_eq!(scrollback.len(), 3);
+    assert_eq!(scrollback.limit(), 3);
+    let lines: Vec<String> = (0..3).map(|idx| text(scrollback.line(idx))).collect();
+    assert_eq!(lines, ["cc", "dd", "ee"]);
+    scrollback.clear();
+    assert!(scrollback.is_empty());
+}
+
+#[test]
+fn no_limit_keeps_nothing() {
+    let mut scrollback = Scrollback::new(0);
+    scrollback.push(line('a'));
+    assert!(scrollback.is_empty());
+    let term = printed(5, 0);
+    assert!(term.scrollback.is_empty());
+}
+
+#[test]
+fn term_history_is_limited() {
+    let term = printed(10, 4);
+    assert_eq!(term.scrollback.len(), 4);
+    // a to h left the screen, only the last 4 are kept
+    assert_eq!(text(term.scrollback.line(0)), "e ");
+    assert_eq!(text(term.scrollback.line(3)), "h ");
+}
+
+#[test]
+fn display_offset_is_clamped() {
+    let mut term = printed(5, 100);
+    assert_eq!(term.scrollback.len(), 3);
+    term.scroll_display(2);
+    assert_eq!(term.display_offset, 2);
+    term.scroll_display(100);
+    assert_eq!(term.display_offset, 3);
+    term.scroll_display(-1);
+    assert_eq!(term.display_offset, 2);
+    term.scroll_display(-100);
+    assert_eq!(term.display_offset, 0);
+    term.scroll_display(1);
+    term.reset_display();
+    assert_eq!(term.display_offset, 0);
+}
+
+#[test]
+fn display_rows_mix_history_and_screen() {
+    let mut term = printed(5, 100);
+    assert_eq!(view(&term), ["d ", "e "]);
+    term.scroll_display(1);
+    assert_eq!(view(&term), ["c ", "d "]);
+    term.scroll_display(2);
+    assert_eq!(view(&term), ["a ", "b "]);
+    // new output keeps the view on the same lines
+    term.advance("\r\nf");
+    assert_eq!(term.display_offset, 4);
+    assert_eq!(view(&term), ["a ", "b "]);
+}
+
+#[test]
+fn erase_saved_lines() {
+    let mut term = printed(5, 100);
+    term.scroll_display(2);
+    term.advance("\x1b[3J");
+    assert!(term.scrollback.is_empty());
+    assert_eq!(term.display_offset, 0);
+    assert_eq!(view(&term), ["d ", "e "]);
+}
@@ -0,0 +1,127 @@
+use hermitshell::terminal::color::{self, cell_colors, DEFAULT_BG, DEFAULT_FG};
+use hermitshell::terminal::grid::{Cell, Color, ATTR_BLINK, ATTR_BOLD, ATTR_DIM,
+                                  ATTR_HIDDEN, ATTR_ITALIC, ATTR_REVERSE,
+                                  ATTR_STRIKE, ATTR_UNDERLINE};
+use hermitshell::terminal::term::Term;
+
+// the cell an 'x' is printed into after the sgr
+fn styled(sgr: &str) -> Cell {
+    let mut term = Term::new(1, 4, 0);
+    term.advance(&format!("\x1b[{}mx", sgr));
+    return *term.grid.cell(0, 0);
+}
+
+#[test]
+fn basic_colours() {
+    assert_eq!(styled("31").fg, Color::Indexed(1));
+    assert_eq!(styled("47").bg, Color::Indexed(7));
+    assert_eq!(styled("92").fg, Color::Indexed(10));
+    assert_eq!(styled("104").bg, Color::Indexed(12));
+}
+
+#[test]
+fn extended_colours() {
+    assert_eq!(styled("38;5;200").fg, Color::Indexed(200));
+    assert_eq!(styled("48;5;17").bg, Color::Indexed(17));
+    assert_eq!(styled("48;2;1;2;3").bg, Color::Rgb(1, 2, 3));
+    assert_eq!(styled("38;2;10;20;30").fg, Color::Rgb(10, 20, 30));
+    // colon forms with and without the colour space id
+    assert_eq!(styled("38:2:10:20:30").fg, Color::Rgb(10, 20, 30));
+    assert_eq!(styled("38:2::10:20:30").fg, Color::Rgb(10, 20, 30));
+    assert_eq!(styled("48:5:9").bg, Color::Indexed(9));
+    // channels are clamped
+    assert_eq!(styled("38;2;999;0;0").fg, Color::Rgb(255, 0, 0));
+    // the params after the colour still apply
+    let cell = styled("38;5;3;1");
+    assert_eq!(cell.fg, Color::Indexed(3));
+    assert_ne!(cell.attrs & ATTR_BOLD, 0);
+    // a cut off colour changes nothing
+    assert_eq!(styled("38;2;1;2").fg, Color::Default);
+}
+
+#[test]
+fn attributes() {
+    let cell = styled("1;2;3;4;5;7;8;9");
+    for attr in [ATTR_BOLD, ATTR_DIM, ATTR_ITALIC, ATTR_UNDERLINE, ATTR_BLINK,
+                 ATTR_REVERSE, ATTR_HIDDEN, ATTR_STRIKE] {
+        assert_ne!(cell.attrs & attr, 0, "{}", attr);
+    }
+    assert_ne!(styled("21").attrs & ATTR_UNDERLINE, 0);
+}
+
+#[test]
+fn underline_subparams() {
+    assert_eq!(styled("4:0").attrs & ATTR_UNDERLINE, 0);
+    assert_ne!(styled("4:3").attrs & ATTR_UNDERLINE, 0);
+    assert_eq!(styled("4;4:0").attrs & ATTR_UNDERLINE, 0);
+}
+
+#[test]
+fn reset_codes() {
+    let all = "1;2;3;4;5;7;8;9;31;41";
+    let cases = [
+        ("22", ATTR_BOLD | ATTR_DIM),
+        ("23", ATTR_ITALIC),
+        ("24", ATTR_UNDERLINE),
+        ("25", ATTR_BLINK),
+        ("27", ATTR_REVERSE),
+        ("28", ATTR_HIDDEN),
+        ("29", ATTR_STRIKE),
+    ];
+    let set = styled(all).attrs;
+    for (code, cleared) in cases {
+        let cell = styled(&format!("{};{}", all, code));
+        assert_eq!(cell.attrs, set & !cleared, "{}", code);
+        assert_eq!((cell.fg, cell.bg), (Color::Indexed(1), Color::Indexed(1)));
+    }
+    let cell = styled(&format!("{};39", all));
+    assert_eq!((cell.fg, cell.bg), (Color::Default, Color::Indexed(1)));
+    let cell = styled(&format!("{};49", all));
+    assert_eq!((cell.fg, cell.bg), (Color::Indexed(1), Color::Default));
+    // 0 and an empty sgr reset everything
+    assert_eq!(styled(&format!("{};0", all)), Cell { c: 'x', ..Default::default() });
+    let mut term = Term::new(1, 4, 0);
+    term.advance(&format!("\x1b[{}m\x1b[mx", all));
+    assert_eq!(*term.grid.cell(0, 0), Cell { c: 'x', ..Default::default() });
+}
+
+fn colors(fg: Color, bg: Color, attrs: u16) -> ([u8; 3], [u8; 3]) {
+    return cell_colors(&Cell { fg, bg, attrs, ..Default::default() });
+}
+
+#[test]
+fn default_colours() {
+    assert_eq!(colors(Color::Default, Color::Default, 0), (DEFAULT_FG, DEFAULT_BG));
+    assert_eq!(colors(Color::Rgb(1, 2, 3), Color::Indexed(196), 0),
+               ([1, 2, 3], [255, 0, 0]));
+    assert_eq!(color::indexed(16), [0, 0, 0]);
+    assert_eq!(color::indexed(231), [255, 255, 255]);
+    assert_eq!(color::indexed(232), [8, 8, 8]);
+    assert_eq!(color::indexed(255), [238, 238, 238]);
+}
+
+#[test]
+fn bold_brightens_the_first_eight() {
+    assert_eq!(colors(Color::Indexed(1), Color::Default, ATTR_BOLD).0,
+               color::indexed(9));
+    // other colours stay as they are
+    assert_eq!(colors(Color::Indexed(9), Color::Default, ATTR_BOLD).0,
+               color::indexed(9));
+    assert_eq!(colors(Color::Indexed(100), Color::Default, ATTR_BOLD).0,
+               color::indexed(100));
+    assert_eq!(colors(Color::Rgb(1, 2, 3), Color::Default, ATTR_BOLD).0, [1, 2, 3]);
+    assert_eq!(colors(Color::Default, Color::Default, ATTR_BOLD).0, DEFAULT_FG);
+}
+
+#[test]
+fn dim_reverse_and_hidden() {
+    assert_eq!(colors(Color::Rgb(90, 150, 255), Color::Default, ATTR_DIM).0,
+               [60, 100, 170]);
+    assert_eq!(colors(Color::Indexed(1), Color::Indexed(2), ATTR_REVERSE),
+               (color::indexed(2), color::indexed(1)));
+    // hidden text is drawn in the bg
+    assert_eq!(colors(Color::Indexed(1), Color::Indexed(2), ATTR_HIDDEN),
+               (color::indexed(2), color::indexed(2)));
+    assert_eq!(colors(Color::Indexed(1), Color::Indexed(2), ATTR_HIDDEN | ATTR_REVERSE),
+               (color::indexed(1), color::indexed(1)));
+}
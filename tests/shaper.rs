@@ -0,0 +1,62 @@
+#![cfg(feature = "shaping")]
+use hermitshell::font_atlas::font_atlas::Face;
+use hermitshell::font_atlas::shaper::Shaper;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+
+// the same font for every face, None if it isn't installed
+fn shaper() -> Option<(Shaper, fontdue::Font)> {
+    let data = std::fs::read(FONT).ok()?;
+    let font = fontdue::Font::from_bytes(data.as_slice(),
+                                         fontdue::FontSettings::default()).ok()?;
+    let data: Rc<[u8]> = Rc::from(data);
+    let fonts: HashMap<Face, Rc<[u8]>> = Face::ALL.iter()
+        .map(|face| (*face, Rc::clone(&data))).collect();
+    return Some((Shaper::new(fonts, 32.0), font));
+}
+
+#[test]
+fn monospace_run() {
+    let Some((mut shaper, font)) = shaper() else { return };
+    let shaped = shaper.shape("a=b", Face::Regular);
+    assert_eq!(shaped.len(), 3);
+    let advance = font.metrics('a', 32.0).advance_width;
+    for (idx, (glpyh, c)) in shaped.iter().zip("a=b".chars()).enumerate() {
+        assert_eq!(glpyh.id, font.lookup_glyph_index(c));
+        assert_eq!(glpyh.cluster, idx);
+        assert_eq!(glpyh.offset, (0.0, 0.0));
+        assert!((glpyh.advance - advance).abs() < 0.01);
+    }
+}
+
+#[test]
+fn marks_join_their_base() {
+    let Some((mut shaper, font)) = shaper() else { return };
+    let shaped = shaper.shape("xe\u{301}", Face::Regular);
+    // the accent may be composed into the base or kept as a mark on it
+    assert!(shaped.iter().skip(1).all(|glpyh| glpyh.cluster == 1));
+    assert_eq!(shaped[0].id, font.lookup_glyph_index('x'));
+}
+
+#[test]
+fn missing_chars_are_zero() {
+    let Some((mut shaper, _)) = shaper() else { return };
+    let shaped = shaper.shape("a\u{e000}", Face::Bold);
+    assert_eq!(shaped.len(), 2);
+    assert_ne!(shaped[0].id, 0);
+    assert_eq!(shaped[1].id, 0);
+    // byte offsets, not char counts
+    assert_eq!(shaped[1].cluster, 1);
+    let shaped = shaper.shape("\u{e000}a", Face::Bold);
+    assert_eq!(shaped[1].cluster, 3);
+}
+
+#[test]
+fn runs_are_cached() {
+    let Some((mut shaper, _)) = shaper() else { return };
+    let first = shaper.shape("cached", Face::Italic);
+    assert!(Rc::ptr_eq(&first, &shaper.shape("cached", Face::Italic)));
+    assert!(!Rc::ptr_eq(&first, &shaper.shape("cached", Face::Regular)));
+}
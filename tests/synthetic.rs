@@ -0,0 +1,69 @@
+use fontdue::Metrics;
+use hermitshell::font_atlas::synthetic::{self, SLANT};
+
+// a 1 pixel wide vertical bar from the baseline up
+fn bar(height: usize, bytes_per_pixel: usize) -> (Metrics, Vec<u8>) {
+    let metrics = Metrics { xmin: 2, ymin: 0, width: 1, height,
+                            ..Default::default() };
+    return (metrics, vec![255; height * bytes_per_pixel]);
+}
+
+#[test]
+fn embolden_widens_strokes() {
+    for bytes_per_pixel in [1, 4] {
+        let (metrics, pixels) = bar(4, bytes_per_pixel);
+        let (bold, out) = synthetic::embolden(metrics, &pixels, bytes_per_pixel, 2);
+        assert_eq!((bold.width, bold.height, bold.xmin, bold.ymin), (3, 4, 2, 0));
+        assert_eq!(out, vec![255; 3 * 4 * bytes_per_pixel]);
+    }
+
+    // keeps the brightest coverage where strokes overlap
+    let metrics = Metrics { width: 3, height: 1, ..Default::default() };
+    let (_, out) = synthetic::embolden(metrics, &[10, 200, 30], 1, 1);
+    assert_eq!(out, vec![10, 200, 200, 30]);
+}
+
+#[test]
+fn oblique_leans_right_above_the_baseline() {
+    let height = 20;
+    let (metrics, pixels) = bar(height, 1);
+    let (italic, out) = synthetic::oblique(metrics, &pixels, 1, SLANT);
+    assert_eq!(italic.height, height);
+    assert_eq!(italic.ymin, 0);
+    assert!(italic.width > 1);
+
+    // where the coverage of a row is centred, relative to the old xmin
+    let centre = |row: usize| -> f32 {
+        let line = &out[row * italic.width..(row + 1) * italic.width];
+        let total: f32 = line.iter().map(|&c| c as f32).sum();
+        let moment: f32 = line.iter().enumerate()
+            .map(|(x, &c)| x as f32 * c as f32).sum();
+        return moment / total + (italic.xmin - metrics.xmin) as f32;
+    };
+    // every row keeps its coverage and moves by its height times the slant
+    for row in 0..height {
+        let line: u32 = out[row * italic.width..(row + 1) * italic.width]
+            .iter().map(|&c| c as u32).sum();
+        assert!(line.abs_diff(255) <= 1);
+        let above = (height - 1 - row) as f32 + 0.5;
+        assert!((centre(row) - above * SLANT).abs() < 0.01);
+    }
+}
+
+#[test]
+fn oblique_leans_left_below_the_baseline() {
+    let metrics = Metrics { xmin: 0, ymin: -10, width: 1, height: 10,
+                            ..Default::default() };
+    let (italic, _) = synthetic::oblique(metrics, &[255; 10], 1, SLANT);
+    assert!(italic.xmin < 0);
+    assert_eq!(italic.ymin, -10);
+}
+
+#[test]
+fn empty_glpyhs_are_left_alone() {
+    let metrics = Metrics::default();
+    let (bold, out) = synthetic::embolden(metrics, &[], 1, 1);
+    assert_eq!((bold.width, out.len()), (0, 0));
+    let (italic, out) = synthetic::oblique(metrics, &[], 4, SLANT);
+    assert_eq!((italic.width, out.len()), (0, 0));
+}
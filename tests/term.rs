@@ -0,0 +1,220 @@
+use hermitshell::terminal::grid::Cursor;
+use hermitshell::terminal::term::Term;
+
+fn row(term: &Term, row: usize) -> String {
+    return term.grid.row(row).iter().map(|cell| cell.c).collect();
+}
+
+fn screen(term: &Term) -> Vec<String> {
+    return (0..term.grid.rows).map(|idx| row(term, idx)).collect();
+}
+
+fn cursor(term: &Term) -> (usize, usize) {
+    let Cursor { row, col } = term.grid.cursor;
+    return (row, col);
+}
+
+// a term with each row filled with its own letter
+fn filled(rows: usize, cols: usize) -> Term {
+    let mut term = Term::new(rows, cols, 0);
+    for idx in 0..rows {
+        let c = (b'a' + idx as u8) as char;
+        term.advance(&format!("\x1b[{}H{}", idx + 1, c.to_string().repeat(cols)));
+    }
+    return term;
+}
+
+#[test]
+fn cursor_movement() {
+    let mut term = Term::new(5, 10, 0);
+    term.advance("\x1b[3;4H");
+    assert_eq!(cursor(&term), (2, 3));
+    term.advance("\x1b[A");
+    assert_eq!(cursor(&term), (1, 3));
+    term.advance("\x1b[2B\x1b[3C");
+    assert_eq!(cursor(&term), (3, 6));
+    term.advance("\x1b[2D");
+    assert_eq!(cursor(&term), (3, 4));
+    term.advance("\x1b[E");
+    assert_eq!(cursor(&term), (4, 0));
+    term.advance("\x1b[2F");
+    assert_eq!(cursor(&term), (2, 0));
+    term.advance("\x1b[7G\x1b[2d");
+    assert_eq!(cursor(&term), (1, 6));
+    // 0 and missing params are 1
+    term.advance("\x1b[0;0H");
+    assert_eq!(cursor(&term), (0, 0));
+    term.advance("\x1b[4;H");
+    assert_eq!(cursor(&term), (3, 0));
+}
+
+#[test]
+fn movement_is_clamped() {
+    let mut term = Term::new(5, 10, 0);
+    term.advance("\x1b[99;99H");
+    assert_eq!(cursor(&term), (4, 9));
+    term.advance("\x1b[65535A\x1b[65535D");
+    assert_eq!(cursor(&term), (0, 0));
+    term.advance("\x1b[65535B\x1b[65535C");
+    assert_eq!(cursor(&term), (4, 9));
+    term.advance("\x1b[65535G\x1b[65535d");
+    assert_eq!(cursor(&term), (4, 9));
+}
+
+#[test]
+fn erase_in_display() {
+    let mut term = filled(3, 4);
+    term.advance("\x1b[2;3H\x1b[J");
+    assert_eq!(screen(&term), ["aaaa", "bb  ", "    "]);
+
+    let mut term = filled(3, 4);
+    term.advance("\x1b[2;3H\x1b[1J");
+    assert_eq!(screen(&term), ["    ", "   b", "cccc"]);
+
+    let mut term = filled(3, 4);
+    term.advance("\x1b[2;3H\x1b[2J");
+    assert_eq!(screen(&term), ["    "; 3]);
+    // ED doesn't move the cursor
+    assert_eq!(cursor(&term), (1, 2));
+}
+
+#[test]
+fn erase_in_line() {
+    let mut term = filled(1, 6);
+    term.advance("\x1b[1;3H\x1b[K");
+    assert_eq!(row(&term, 0), "aa    ");
+    let mut term = filled(1, 6);
+    term.advance("\x1b[1;3H\x1b[1K");
+    assert_eq!(row(&term, 0), "   aaa");
+    let mut term = filled(1, 6);
+    term.advance("\x1b[1;3H\x1b[2K");
+    assert_eq!(row(&term, 0), "      ");
+}
+
+#[test]
+fn erase_chars() {
+    let mut term = filled(1, 6);
+    term.advance("\x1b[1;2H\x1b[2X");
+    assert_eq!(row(&term, 0), "a  aaa");
+    term.advance("\x1b[9999X");
+    assert_eq!(row(&term, 0), "a     ");
+    assert_eq!(cursor(&term), (0, 1));
+}
+
+#[test]
+fn insert_and_delete_chars() {
+    let mut term = Term::new(1, 6, 0);
+    term.advance("abcdef\x1b[1;2H\x1b[2@");
+    assert_eq!(row(&term, 0), "a  bcd");
+    term.advance("\x1b[3P");
+    assert_eq!(row(&term, 0), "acd   ");
+    term.advance("\x1b[9999@");
+    assert_eq!(row(&term, 0), "a     ");
+    term.advance("\x1b[1;1Habcdef\x1b[1;3H\x1b[9999P");
+    assert_eq!(row(&term, 0), "ab    ");
+}
+
+#[test]
+fn insert_and_delete_lines() {
+    let mut term = filled(4, 2);
+    term.advance("\x1b[2;2H\x1b[L");
+    assert_eq!(screen(&term), ["aa", "  ", "bb", "cc"]);
+    // IL and DL go to the start of the line
+    assert_eq!(cursor(&term), (1, 0));
+    term.advance("\x1b[2M");
+    assert_eq!(screen(&term), ["aa", "cc", "  ", "  "]);
+    term.advance("\x1b[9999L");
+    assert_eq!(screen(&term), ["aa", "  ", "  ", "  "]);
+}
+
+#[test]
+fn scroll_region() {
+    let mut term = filled(5, 2);
+    // DECSTBM homes the cursor
+    term.advance("\x1b[2;4r");
+    assert_eq!(cursor(&term), (0, 0));
+    term.advance("\x1b[4;1H\n");
+    assert_eq!(screen(&term), ["aa", "cc", "dd", "  ", "ee"]);
+    term.advance("\x1b[S");
+    assert_eq!(screen(&term), ["aa", "dd", "  ", "  ", "ee"]);
+    term.advance("\x1b[2T");
+    assert_eq!(screen(&term), ["aa", "  ", "  ", "dd", "ee"]);
+    // RI at the top of the region scrolls it down
+    term.advance("\x1b[4;1Hx\x1b[2;1H\x1bM");
+    assert_eq!(screen(&term), ["aa", "  ", "  ", "  ", "ee"]);
+    // lines outside the region don't scroll
+    term.advance("\x1b[5;1H\n\n");
+    assert_eq!(screen(&term), ["aa", "  ", "  ", "  ", "ee"]);
+    assert_eq!(cursor(&term), (4, 0));
+    // an invalid region is ignored
+    term.advance("\x1b[3;3r\x1b[4;1H\n");
+    assert_eq!(cursor(&term), (3, 0));
+    // a huge bottom is the last row
+    term.advance("\x1b[1;65535r\x1b[5;1H\n");
+    assert_eq!(screen(&term), ["  ", "  ", "  ", "ee", "  "]);
+}
+
+#[test]
+fn huge_scroll() {
+    let mut term = filled(3, 2);
+    term.advance("\x1b[65535S");
+    assert_eq!(screen(&term), ["  "; 3]);
+    let mut term = filled(3, 2);
+    term.advance("\x1b[65535T");
+    assert_eq!(screen(&term), ["  "; 3]);
+}
+
+#[test]
+fn tab_stops() {
+    let mut term = Term::new(1, 20, 0);
+    term.advance("\t");
+    assert_eq!(cursor(&term), (0, 8));
+    term.advance("\t\t");
+    // the last col stops a tab
+    assert_eq!(cursor(&term), (0, 19));
+    // HTS sets a stop, TBC clears one or all
+    term.advance("\r\x1b[4C\x1bH\r\t");
+    assert_eq!(cursor(&term), (0, 4));
+    term.advance("\x1b[g\r\t");
+    assert_eq!(cursor(&term), (0, 8));
+    term.advance("\x1b[3g\r\t");
+    assert_eq!(cursor(&term), (0, 19));
+}
+
+#[test]
+fn autowrap() {
+    let mut term = Term::new(2, 4, 0);
+    term.advance("abcd");
+    // the wrap waits for the next char
+    assert_eq!(cursor(&term), (0, 3));
+    term.advance("e");
+    assert_eq!(screen(&term), ["abcd", "e   "]);
+    // a cr before it cancels the wrap
+    term.advance("\x1b[1;1Hwxyz\rA");
+    assert_eq!(screen(&term), ["Axyz", "e   "]);
+
+    // DECAWM off overwrites the last col
+    let mut term = Term::new(2, 4, 0);
+    term.advance("\x1b[?7labcdef");
+    assert_eq!(screen(&term), ["abcf", "    "]);
+    term.advance("\x1b[?7h\x1b[1;4Hgh");
+    assert_eq!(screen(&term), ["abcg", "h   "]);
+}
+
+#[test]
+fn one_by_one_grid() {
+    let mut term = Term::new(1, 1, 10);
+    term.advance("ab漢\t\x1b[9999@\x1b[9999P\x1b[65535S\x1b[65535T\x1b[9999X");
+    term.advance("\x1b[9999;9999H\x1b[9999L\x1b[9999M\x1b[J\x1b[1J\x1b[K\x1bM\x1bD");
+    term.advance("\x1b[1;1r\x1b[?1049h\x1b[?1049lz");
+    assert_eq!(screen(&term), ["z"]);
+    assert_eq!(cursor(&term), (0, 0));
+}
+
+#[test]
+fn dcs_payload_is_dropped() {
+    let mut term = Term::new(3, 10, 0);
+    term.advance("a\x1bP1$qm\x1b\\b\x1bPq#0;2;0;0;0~~@@\x1b\\c");
+    assert_eq!(row(&term, 0), "abc       ");
+    assert_eq!(cursor(&term), (0, 3));
+}
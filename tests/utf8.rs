@@ -0,0 +1,45 @@
+use hermitshell::decode_utf8;
+
+#[test]
+fn whole_input() {
+    let mut pending = "a漢b".as_bytes().to_vec();
+    assert_eq!(decode_utf8(&mut pending), "a漢b");
+    assert!(pending.is_empty());
+}
+
+#[test]
+fn char_split_across_reads() {
+    let bytes = "x漢y".as_bytes();
+    // the 3 byte char is cut after its first byte
+    let mut pending = bytes[..2].to_vec();
+    assert_eq!(decode_utf8(&mut pending), "x");
+    assert_eq!(pending, &bytes[1..2]);
+    pending.extend_from_slice(&bytes[2..]);
+    assert_eq!(decode_utf8(&mut pending), "漢y");
+    assert!(pending.is_empty());
+}
+
+#[test]
+fn trailing_partial_sequence_is_kept() {
+    let bytes = "ok😀".as_bytes();
+    for cut in 3..bytes.len() {
+        let mut pending = bytes[..cut].to_vec();
+        assert_eq!(decode_utf8(&mut pending), "ok");
+        assert_eq!(pending, &bytes[2..cut]);
+    }
+}
+
+#[test]
+fn invalid_bytes_are_replaced() {
+    // 0xff can't start a sequence, the ascii after it is kept
+    let mut pending = b"a\xffbc".to_vec();
+    assert_eq!(decode_utf8(&mut pending), "a\u{fffd}bc");
+    assert!(pending.is_empty());
+    // a lead byte followed by ascii instead of a continuation
+    let mut pending = b"\xe6x".to_vec();
+    assert_eq!(decode_utf8(&mut pending), "\u{fffd}x");
+    // a stray continuation byte
+    let mut pending = b"\x80\x80z".to_vec();
+    assert_eq!(decode_utf8(&mut pending), "\u{fffd}\u{fffd}z");
+    assert!(pending.is_empty());
+}
@@ -0,0 +1,119 @@
+use hermitshell::terminal::grid::Cell;
+use hermitshell::terminal::term::Term;
+
+// chars of a row with spacers as '_'
+fn row(term: &Term, row: usize) -> String {
+    return term.grid.row(row).iter()
+        .map(|cell| if cell.is_spacer() { '_' } else { cell.c }).collect();
+}
+
+fn cell(term: &Term, row: usize, col: usize) -> Cell {
+    return *term.grid.cell(row, col);
+}
+
+#[test]
+fn wide_chars_take_two_cells() {
+    let mut term = Term::new(2, 8, 0);
+    term.advance("a漢b");
+    assert_eq!(row(&term, 0), "a漢_b    ");
+    assert_eq!(cell(&term, 0, 1).width, 2);
+    assert_eq!(cell(&term, 0, 2).width, 0);
+    assert_eq!(cell(&term, 0, 3).width, 1);
+    assert_eq!(term.grid.cursor.col, 4);
+
+    // the spacer keeps the attributes of its char
+    term.advance("\r\n\x1b[1;41m字");
+    assert_eq!((cell(&term, 1, 0).attrs, cell(&term, 1, 0).bg),
+               (cell(&term, 1, 1).attrs, cell(&term, 1, 1).bg));
+}
+
+#[test]
+fn wide_chars_wrap_whole() {
+    let mut term = Term::new(2, 4, 0);
+    term.advance("abc字");
+    assert_eq!(row(&term, 0), "abc ");
+    assert_eq!(row(&term, 1), "字_  ");
+    assert_eq!(term.grid.cursor.col, 2);
+
+    // filling the line leaves the cursor on the spacer until the next print
+    let mut term = Term::new(2, 4, 0);
+    term.advance("ab字");
+    assert_eq!(row(&term, 0), "ab字_");
+    assert_eq!((term.grid.cursor.row, term.grid.cursor.col), (0, 3));
+    term.advance("c");
+    assert_eq!(row(&term, 1), "c   ");
+
+    // without autowrap it goes in the last two columns
+    let mut term = Term::new(2, 4, 0);
+    term.advance("\x1b[?7labc字");
+    assert_eq!(row(&term, 0), "ab字_");
+}
+
+#[test]
+fn overwriting_half_a_wide_char_clears_it() {
+    let mut term = Term::new(1, 6, 0);
+    term.advance("漢字\x1b[1Gx");
+    assert_eq!(row(&term, 0), "x 字_  ");
+    term.advance("\x1b[4Gy");
+    assert_eq!(row(&term, 0), "x  y  ");
+    // a wide char over two halves clears both neighbours
+    term.advance("\x1b[1G漢字\x1b[2G字");
+    assert_eq!(row(&term, 0), " 字_   ");
+}
+
+#[test]
+fn marks_join_the_cell_before() {
+    let mut term = Term::new(1, 6, 0);
+    term.advance("e\u{301}x");
+    assert_eq!(row(&term, 0), "ex    ");
+    assert_eq!(cell(&term, 0, 0).text(), "e\u{301}");
+    assert!(!cell(&term, 0, 1).has_marks());
+    assert_eq!(term.grid.cursor.col, 2);
+
+    // on a wide char the mark goes on the char, not its spacer
+    term.advance("漢\u{302}");
+    assert_eq!(cell(&term, 0, 2).text(), "漢\u{302}");
+    assert!(!cell(&term, 0, 3).has_marks());
+
+    // at the end of the line the mark goes on the last char
+    term.advance("ab\u{303}");
+    assert_eq!(cell(&term, 0, 5).text(), "b\u{303}");
+
+    // marks with nothing before them are dropped, extra marks too
+    let mut term = Term::new(1, 4, 0);
+    term.advance("\u{301}a\u{301}\u{302}\u{303}\u{304}");
+    assert_eq!(row(&term, 0), "a   ");
+    assert_eq!(cell(&term, 0, 0).text(), "a\u{301}\u{302}");
+}
+
+#[test]
+fn shrinking_cols_doesnt_cut_a_wide_char() {
+    let mut term = Term::new(1, 4, 0);
+    term.advance("a漢");
+    term.resize(1, 2);
+    assert_eq!(row(&term, 0), "a ");
+    assert_eq!(cell(&term, 0, 1).width, 1);
+}
+
+#[test]
+fn inserting_and_deleting_split_wide_chars() {
+    // inserting on a spacer blanks the char it belongs to
+    let mut term = Term::new(1, 6, 0);
+    term.advance("漢字\x1b[2G\x1b[@");
+    assert_eq!(row(&term, 0), "   字_ ");
+
+    // a wide char half pushed off the line is blanked
+    let mut term = Term::new(1, 4, 0);
+    term.advance("ab漢\x1b[1G\x1b[@");
+    assert_eq!(row(&term, 0), " ab ");
+
+    // deleting the start of a wide char blanks its spacer
+    let mut term = Term::new(1, 6, 0);
+    term.advance("漢字ab\x1b[1G\x1b[P");
+    assert_eq!(row(&term, 0), " 字_ab ");
+
+    // deleting from a spacer blanks the char before it
+    let mut term = Term::new(1, 6, 0);
+    term.advance("漢字ab\x1b[2G\x1b[2P");
+    assert_eq!(row(&term, 0), "  ab  ");
+}